@@ -0,0 +1,144 @@
+/**
+ * UI-Ground SDK: Columnar Encoder
+ * Packs records into typed arrays for WasmUiDb.ingest_columnar
+ */
+
+import { ElementRole, NodeRecord } from "./types.js";
+
+/**
+ * Packed columnar batch understood by WasmUiDb.ingest_columnar.
+ * Strings are stored once in a UTF-8 string table and referenced by index.
+ */
+export interface ColumnarBatch {
+    ids: Uint32Array;
+    frameIds: Uint16Array;
+    /** Role codes (position of the role in ElementRole) */
+    roles: Uint8Array;
+    stateBits: Uint32Array;
+    /** x, y, width, height per record */
+    rects: Int32Array;
+    names: Uint32Array;
+    fingerprints: Uint32Array;
+    tagNames: Uint32Array;
+    /** n + 1 offsets into contextIds */
+    contextOffsets: Uint32Array;
+    contextIds: Uint32Array;
+    /** n + 1 offsets into attrIds, counted in key/value pairs */
+    attrOffsets: Uint32Array;
+    attrIds: Uint32Array;
+    stringData: Uint8Array;
+    /** String count + 1 byte offsets into stringData */
+    stringOffsets: Uint32Array;
+}
+
+const ROLE_CODES = new Map<ElementRole, number>(
+    Object.values(ElementRole).map((role, code) => [role, code])
+);
+
+/**
+ * Encode records into a columnar batch
+ */
+export function encodeColumnarBatch(records: NodeRecord[]): ColumnarBatch {
+    const n = records.length;
+    const strings: string[] = [];
+    const stringIds = new Map<string, number>();
+
+    const intern = (value: string): number => {
+        let id = stringIds.get(value);
+        if (id === undefined) {
+            id = strings.length;
+            strings.push(value);
+            stringIds.set(value, id);
+        }
+        return id;
+    };
+
+    const batch = {
+        ids: new Uint32Array(n),
+        frameIds: new Uint16Array(n),
+        roles: new Uint8Array(n),
+        stateBits: new Uint32Array(n),
+        rects: new Int32Array(n * 4),
+        names: new Uint32Array(n),
+        fingerprints: new Uint32Array(n),
+        tagNames: new Uint32Array(n),
+        contextOffsets: new Uint32Array(n + 1),
+        attrOffsets: new Uint32Array(n + 1),
+    };
+    const contextIds: number[] = [];
+    const attrIds: number[] = [];
+
+    records.forEach((record, i) => {
+        const role = ROLE_CODES.get(record.role);
+        if (role === undefined) {
+            throw new Error(`Unknown role: ${record.role}`);
+        }
+
+        batch.ids[i] = record.id;
+        batch.frameIds[i] = record.frameId;
+        batch.roles[i] = role;
+        batch.stateBits[i] = record.stateBits;
+        batch.rects.set(
+            [record.rect.x, record.rect.y, record.rect.width, record.rect.height],
+            i * 4
+        );
+        batch.names[i] = intern(record.name);
+        batch.fingerprints[i] = intern(record.fingerprint);
+        batch.tagNames[i] = intern(record.tagName);
+
+        for (const ctx of record.context) {
+            contextIds.push(intern(ctx));
+        }
+        batch.contextOffsets[i + 1] = contextIds.length;
+
+        for (const [key, value] of Object.entries(record.attrs)) {
+            attrIds.push(intern(key), intern(value));
+        }
+        batch.attrOffsets[i + 1] = attrIds.length / 2;
+    });
+
+    // Size the string table up front, then encode every string in place
+    const stringOffsets = new Uint32Array(strings.length + 1);
+    strings.forEach((value, i) => {
+        stringOffsets[i + 1] = stringOffsets[i] + utf8Length(value);
+    });
+    const stringData = new Uint8Array(stringOffsets[strings.length]);
+    const encoder = new TextEncoder();
+    strings.forEach((value, i) => {
+        encoder.encodeInto(value, stringData.subarray(stringOffsets[i]));
+    });
+
+    return {
+        ...batch,
+        contextIds: Uint32Array.from(contextIds),
+        attrIds: Uint32Array.from(attrIds),
+        stringData,
+        stringOffsets,
+    };
+}
+
+/**
+ * UTF-8 byte length of a string, matching TextEncoder (lone surrogates become U+FFFD)
+ */
+function utf8Length(value: string): number {
+    let length = 0;
+    for (let i = 0; i < value.length; i++) {
+        const code = value.charCodeAt(i);
+        if (code < 0x80) {
+            length += 1;
+        } else if (code < 0x800) {
+            length += 2;
+        } else if (code >= 0xd800 && code <= 0xdbff && i + 1 < value.length) {
+            const next = value.charCodeAt(i + 1);
+            if (next >= 0xdc00 && next <= 0xdfff) {
+                length += 4;
+                i++;
+            } else {
+                length += 3;
+            }
+        } else {
+            length += 3;
+        }
+    }
+    return length;
+}
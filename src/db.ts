@@ -82,6 +82,41 @@ function fuzzyScore(query: string, target: string): number {
     return Math.max(tokenScore * 0.7, levScore * 0.5);
 }
 
+/** Temperature of softmax scores; a raw score lead of 0.05 makes a match e times as likely */
+const SOFTMAX_TEMPERATURE = 0.05;
+
+/**
+ * Text folding for a filter: lowercase unless it asks for case-sensitive matching
+ */
+function caseFold(caseSensitive?: boolean): (text: string) => string {
+    return caseSensitive ? (text) => text : (text) => text.toLowerCase();
+}
+
+/**
+ * Whole-word match: the pattern's words appear consecutively in the text
+ */
+function wordMatch(pattern: string, text: string): boolean {
+    const words = (s: string) => s.split(/[^\p{L}\p{N}]+/u).filter(Boolean);
+    const p = words(pattern);
+    const t = words(text);
+    if (p.length === 0) return false;
+    for (let i = 0; i + p.length <= t.length; i++) {
+        if (p.every((w, j) => t[i + j] === w)) return true;
+    }
+    return false;
+}
+
+/**
+ * Translate a glob (`*` any run, `?` one character) into an anchored RegExp
+ */
+function globToRegExp(glob: string): RegExp {
+    const source = glob
+        .replace(/[.+^${}()|[\]\\]/g, "\\$&")
+        .replace(/\*/g, ".*")
+        .replace(/\?/g, ".");
+    return new RegExp(`^${source}$`, "s");
+}
+
 /**
  * UI Database with indexing and query execution
  */
@@ -218,12 +253,25 @@ export class UiDatabase {
         }
 
         // Score candidates
-        const scored: Array<{ index: number; score: number }> = [];
+        let scored: Array<{ index: number; score: number }> = [];
         for (const index of candidates) {
             const score = this.scoreCandidate(index, q);
             scored.push({ index, score });
         }
 
+        if (q.score_mode === "softmax" && scored.length > 0) {
+            // Shift by the best score so exp() can't overflow
+            const best = Math.max(...scored.map((s) => s.score));
+            const weights = scored.map((s) => Math.exp((s.score - best) / SOFTMAX_TEMPERATURE));
+            const sum = weights.reduce((a, b) => a + b, 0);
+            scored.forEach((s, i) => (s.score = weights[i] / sum));
+        }
+        if (q.min_score !== undefined) {
+            const minScore = q.min_score;
+            filtersApplied.push(`min_score(${minScore})`);
+            scored = scored.filter((s) => s.score >= minScore);
+        }
+
         // Sort by score (desc) or other criteria
         if (q.order_by && q.order_by.length > 0) {
             const order = q.order_by[0];
@@ -265,7 +313,7 @@ export class UiDatabase {
 
         return {
             matches,
-            total: candidates.size,
+            total: scored.length,
             explain: {
                 candidatesConsidered: candidates.size,
                 filtersApplied,
@@ -348,11 +396,12 @@ export class UiDatabase {
             }
 
             // Handle pipe-separated alternatives
-            const alternatives = value.split("|").map((v) => v.trim().toLowerCase());
+            const fold = caseFold(clause.name.case_sensitive);
+            const alternatives = value.split("|").map((v) => fold(v.trim()));
 
             for (let i = 0; i < this.records.length; i++) {
                 const record = this.records[i];
-                const name = record.name.toLowerCase();
+                const name = fold(record.name);
 
                 if (this.matchText(name, alternatives, match)) {
                     result.add(i);
@@ -366,11 +415,12 @@ export class UiDatabase {
             const { match, value } = clause.in_context;
             filtersApplied.push(`context(${match}:${value})`);
 
-            const alternatives = value.split("|").map((v) => v.trim().toLowerCase());
+            const fold = caseFold(clause.in_context.case_sensitive);
+            const alternatives = value.split("|").map((v) => fold(v.trim()));
 
             for (let i = 0; i < this.records.length; i++) {
                 const record = this.records[i];
-                const contextText = record.context.join(" ").toLowerCase();
+                const contextText = fold(record.context.join(" "));
 
                 if (this.matchText(contextText, alternatives, match)) {
                     result.add(i);
@@ -383,12 +433,13 @@ export class UiDatabase {
         if ("attr" in clause) {
             const { name, value, match } = clause.attr;
             filtersApplied.push(`attr(${name}=${value})`);
+            const fold = caseFold(clause.attr.case_sensitive);
 
             for (let i = 0; i < this.records.length; i++) {
                 const record = this.records[i];
                 const attrValue = record.attrs[name];
                 if (attrValue) {
-                    if (this.matchText(attrValue.toLowerCase(), [value.toLowerCase()], match ?? "exact")) {
+                    if (this.matchText(fold(attrValue), [fold(value)], match ?? "exact")) {
                         result.add(i);
                     }
                 }
@@ -468,8 +519,23 @@ export class UiDatabase {
                     if (text.includes(pattern)) return true;
                     break;
                 case "fuzzy":
+                case "lsh":
+                case "phonetic":
+                    // No MinHash or phonetic index in the JS engine; approximate with fuzzy
                     if (fuzzyScore(pattern, text) > 0.5) return true;
                     break;
+                case "starts_with":
+                    if (text.startsWith(pattern)) return true;
+                    break;
+                case "ends_with":
+                    if (text.endsWith(pattern)) return true;
+                    break;
+                case "word":
+                    if (wordMatch(pattern, text)) return true;
+                    break;
+                case "glob":
+                    if (globToRegExp(pattern).test(text)) return true;
+                    break;
                 case "regex":
                     try {
                         if (new RegExp(pattern, "i").test(text)) return true;
@@ -529,7 +595,7 @@ export class UiDatabase {
             score += 0.05;
         }
 
-        return Math.min(1.0, score);
+        return q.score_mode === "raw" || q.score_mode === "softmax" ? score : Math.min(1.0, score);
     }
 
     /**
@@ -12,6 +12,7 @@ export { UiDatabase } from "./db.js";
 export { EmbeddingService, EMBEDDING_DIMS, type IEmbeddingService } from "./embedding-service.js";
 export { QueryOrchestrator, type SemanticQueryAST } from "./orchestrator.js";
 export { IconMatcher } from "./icon-matcher.js";
+export { encodeColumnarBatch, type ColumnarBatch } from "./columnar.js";
 
 // Worker-based embedding (SharedWorker for cross-tab model sharing)
 export {
@@ -65,6 +66,20 @@ export class UiGround {
             }
         }
 
+        // Outside the try above: a bad profile or text option is a config
+        // error to surface, not a reason to fall back to JS
+        if (this.wasmDb) {
+            if (this.config.synonymProfile) {
+                const profile = this.config.synonymProfile;
+                this.wasmDb.set_synonym_profile?.(
+                    Array.isArray(profile) ? profile.join("+") : profile
+                );
+            }
+            if (this.config.textOptions) {
+                this.wasmDb.set_text_options?.(this.config.textOptions);
+            }
+        }
+
         if (preference === "js") {
             this._engineType = "js";
         } else if (preference === "wasm") {
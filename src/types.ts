@@ -123,7 +123,22 @@ export interface SemanticLocator {
 // Query AST Types
 // ============================================================================
 
-export type MatchType = "exact" | "contains" | "fuzzy" | "regex";
+/**
+ * "lsh" finds near-duplicate names via MinHash; "phonetic" finds sound-alike
+ * words via Double Metaphone and Kölner Phonetik (WASM engine; JS falls back to fuzzy).
+ * "word" matches whole words only; "glob" supports `*` and `?` over the whole text.
+ */
+export type MatchType =
+    | "exact"
+    | "contains"
+    | "fuzzy"
+    | "regex"
+    | "lsh"
+    | "phonetic"
+    | "starts_with"
+    | "ends_with"
+    | "word"
+    | "glob";
 
 export interface RoleFilter {
     role: ElementRole | ElementRole[];
@@ -147,6 +162,8 @@ export interface NameFilter {
     name: {
         match: MatchType;
         value: string;
+        /** Match case exactly, e.g. "ID" but not "id" (default: false) */
+        case_sensitive?: boolean;
     };
 }
 
@@ -154,6 +171,8 @@ export interface ContextFilter {
     in_context: {
         match: MatchType;
         value: string;
+        /** Match case exactly, e.g. "ID" but not "id" (default: false) */
+        case_sensitive?: boolean;
     };
 }
 
@@ -162,6 +181,8 @@ export interface AttrFilter {
         name: string;
         value: string;
         match?: MatchType;
+        /** Match case exactly (default: false) */
+        case_sensitive?: boolean;
     };
 }
 
@@ -197,6 +218,19 @@ export interface QueryAST {
     order_by?: OrderBy[];
     limit?: number;
     offset?: number;
+    /** Retry a query without matches using its spelling suggestions (WASM engine) */
+    autocorrect?: boolean;
+    /** Weaken a query without matches (exact -> contains -> fuzzy, then drop context, then state) until something matches (WASM engine) */
+    relax?: boolean;
+    /** Score gap under which leading matches are reported as ambiguous (default: 0.06) */
+    ambiguity_gap?: number;
+    /** Drop matches whose reported score is below this */
+    min_score?: number;
+    /**
+     * "clamped" caps scores at 1.0 (default), "raw" reports them uncapped, and
+     * "softmax" reports relative confidences across all matches that sum to 1
+     */
+    score_mode?: "clamped" | "raw" | "softmax";
 }
 
 // ============================================================================
@@ -228,6 +262,21 @@ export interface MatchResult {
     actionability: Actionability;
     rect: Rect;
     record: NodeRecord;
+    /** Text ranges the name, context and attr filters matched (WASM engine) */
+    highlights?: Highlight[];
+}
+
+/** Matched part of a record's text; offsets are UTF-16 string indices */
+export interface Highlight {
+    field: "name" | "context" | "attr";
+    /** Context entry the range falls in */
+    index?: number;
+    /** Attribute whose value the range falls in */
+    attr?: string;
+    start: number;
+    end: number;
+    /** Query text, or the synonym it expanded to, that matched */
+    term: string;
 }
 
 export interface SemanticQueryAST extends QueryAST {
@@ -238,27 +287,99 @@ export interface SemanticQueryAST extends QueryAST {
     };
 }
 
+/** Synonym that matched at least one candidate */
+export interface SynonymUse {
+    term: string;
+    synonym: string;
+    /** Edge weight applied to the match score */
+    weight: number;
+}
+
 export interface QueryExplain {
     candidatesConsidered: number;
     filtersApplied: string[];
     executionTimeMs: number;
+    synonymsUsed?: SynonymUse[];
+    /** "Did you mean" page words for unknown name words, when nothing matched */
+    spellingSuggestions?: SpellingSuggestion[];
+    /** Matches come from retrying with the suggested words */
+    autocorrected?: boolean;
+    /** Relaxations applied, in order, when the query matched nothing as given */
+    relaxations?: string[];
+}
+
+export interface SpellingSuggestion {
+    /** Normalized word from the query */
+    token: string;
+    /** Nearest page words, closest and most frequent first */
+    suggestions: string[];
 }
 
 export interface QueryResult {
     matches: MatchResult[];
     total: number;
+    /** Present when the leading matches score too close to tell apart (WASM engine) */
+    ambiguity?: Ambiguity;
     explain: QueryExplain;
 }
 
+export interface Ambiguity {
+    /** Score difference between the best and the last near-tied match */
+    scoreGap: number;
+    candidates: Disambiguation[];
+}
+
+/** What sets one of several near-tied matches apart from the others */
+export interface Disambiguation {
+    id: number;
+    /** Context strings no other near-tied match has */
+    context: string[];
+    /** Name of the closest heading above the element */
+    heading?: string;
+    /** 1-based position among the near-tied matches, top to bottom then left to right */
+    position: number;
+    rect: Rect;
+    testid?: string;
+    /** Clauses that, added to the query, keep this match and drop the other near-tied ones */
+    suggestedWhere: WhereClause[];
+}
+
 // ============================================================================
 // Configuration Types
 // ============================================================================
 
+/** Built-in synonym profiles */
+export type SynonymProfile =
+    | "de-en-ui"
+    | "fr-ui"
+    | "es-ui"
+    | "it-ui"
+    | "nl-ui"
+    | "pt-ui"
+    | "app-specific"
+    | "none";
+
+/** Text normalization settings; NFKC and lowercasing always apply */
+export interface TextOptions {
+    /** Spell out German umlauts and ß, e.g. "löschen" matches "loeschen" (default: true) */
+    transliterateGerman?: boolean;
+    /** Strip diacritics, e.g. "café" matches "cafe" (default: true) */
+    foldDiacritics?: boolean;
+    /** Snowball stemming language; stem-only matches score lower (default: off) */
+    stemming?: "en" | "de" | "fr" | "es" | "it" | "nl" | "pt" | null;
+    /** Also index context text by trigrams to speed up inContext queries (default: false) */
+    contextTrigrams?: boolean;
+    /** BCP 47 tag whose case rules lowercasing follows, e.g. "tr" for dotless ı (default: root rules) */
+    locale?: string;
+}
+
 export interface UiDbConfig {
     /** Enable LSH fuzzy matching (default: false for MVP) */
     enableLSH?: boolean;
-    /** Synonym profile for multilingual matching */
-    synonymProfile?: "de-en-ui" | "app-specific" | "none";
+    /** Synonym profile(s) for multilingual matching; arrays combine packs */
+    synonymProfile?: SynonymProfile | SynonymProfile[];
+    /** Text normalization used for indexing and matching (WASM engine) */
+    textOptions?: TextOptions;
     /** Only include elements in viewport */
     viewportOnly?: boolean;
     /** Custom element filter */
@@ -287,15 +408,39 @@ export interface UiDbConfig {
 /** Interface for the WASM database module */
 export interface WasmDbModule {
     new(): WasmDbInstance;
+    /** Restore a database from a snapshot produced by `to_bytes` */
+    from_bytes?(bytes: Uint8Array): WasmDbInstance;
 }
 
 /** Interface for a WASM database instance */
 export interface WasmDbInstance {
     ingest(records: unknown): void;
+    /** Ingest a packed batch from encodeColumnarBatch */
+    ingest_columnar?(batch: unknown): void;
+    /** Replace the synonym table with a built-in profile */
+    set_synonym_profile?(profile: string): void;
+    add_synonym_profile?(profile: string): void;
+    set_text_options?(options: TextOptions): void;
+    /** Merge custom synonym groups, given as JSON array of string arrays */
+    load_synonyms?(groupsJson: string): void;
+    /** Remove all synonyms */
+    clear_synonyms?(): void;
     query(query: unknown): unknown;
+    /** Top `k` indexed words starting with `prefix`, by document frequency */
+    suggest?(prefix: string, k: number, role?: ElementRole): TermFrequency[];
+    /** All indexed words with their document frequencies */
+    vocabulary?(): TermFrequency[];
+    /** Serialize records, indices and synonyms into a binary snapshot */
+    to_bytes?(includeEmbeddings: boolean): Uint8Array;
     reset(): void;
 }
 
+/** Indexed word and the number of records containing it */
+export interface TermFrequency {
+    term: string;
+    frequency: number;
+}
+
 /** Engine types for query execution */
 export type EngineType = "wasm" | "js";
 
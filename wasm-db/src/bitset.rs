@@ -0,0 +1,139 @@
+//! Dense bitsets over record indices for candidate sets
+
+/// Fixed-size set of record indices backed by 64-bit words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitset {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitset {
+    /// Empty set over `len` records
+    pub fn empty(len: usize) -> Self {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    /// Set containing every record index below `len`
+    pub fn full(len: usize) -> Self {
+        let mut set = Bitset {
+            words: vec![u64::MAX; len.div_ceil(64)],
+            len,
+        };
+        set.trim();
+        set
+    }
+
+    /// Clear bits past `len` in the last word
+    fn trim(&mut self) {
+        let tail = self.len % 64;
+        if tail != 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1u64 << tail) - 1;
+            }
+        }
+    }
+
+    pub fn insert(&mut self, idx: usize) {
+        self.words[idx / 64] |= 1u64 << (idx % 64);
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < self.len && self.words[idx / 64] & (1u64 << (idx % 64)) != 0
+    }
+
+    /// Number of records in the set
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Remove every record
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Keep only records also in `other`
+    pub fn intersect_with(&mut self, other: &Bitset) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= b;
+        }
+    }
+
+    /// Add every record that is in `other`
+    pub fn union_with(&mut self, other: &Bitset) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    /// Remove every record that is in `other`
+    pub fn subtract(&mut self, other: &Bitset) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= !b;
+        }
+    }
+
+    /// Keep only records for which `keep` returns true, visiting set bits only
+    pub fn retain(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        for (w, word) in self.words.iter_mut().enumerate() {
+            let mut bits = *word;
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                if !keep(w * 64 + bit) {
+                    *word &= !(1u64 << bit);
+                }
+            }
+        }
+    }
+
+    /// Iterate set indices in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, &word)| {
+            let mut bits = word;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(w * 64 + bit)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_and_count() {
+        let set = Bitset::full(70);
+        assert_eq!(set.count(), 70);
+        assert!(set.contains(69));
+        assert!(!set.contains(70));
+        assert_eq!(Bitset::empty(70).count(), 0);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let mut a = Bitset::full(130);
+        let mut b = Bitset::empty(130);
+        b.insert(3);
+        b.insert(64);
+        b.insert(129);
+
+        a.subtract(&b);
+        assert_eq!(a.count(), 127);
+        assert!(!a.contains(64));
+
+        a.intersect_with(&b);
+        assert_eq!(a.count(), 0);
+
+        b.retain(|idx| idx != 64);
+        assert_eq!(b.iter().collect::<Vec<_>>(), vec![3, 129]);
+    }
+}
@@ -1,6 +1,7 @@
 //! LRU cache for fingerprint → embedding mapping
 
 use std::collections::{HashMap, VecDeque};
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
 
 /// Entry in the embedding cache
 #[derive(Clone)]
@@ -109,6 +110,52 @@ impl EmbeddingCache {
     pub fn capacity(&self) -> usize {
         self.capacity
     }
+
+    /// Serialize entries from least to most recently used
+    pub fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        w.put_len(self.capacity);
+        w.put_len(self.access_order.len());
+        for fingerprint in &self.access_order {
+            let entry = &self.entries[fingerprint];
+            w.put_str(fingerprint);
+            w.put_varint(entry.access_count as u64);
+            w.put_len(entry.embedding.len());
+            for &value in &entry.embedding {
+                w.put_f32(value);
+            }
+        }
+    }
+
+    /// Rebuild a cache from a snapshot, preserving LRU order
+    pub fn read_snapshot(r: &mut SnapshotReader) -> Result<Self, String> {
+        let capacity = r.varint()? as usize;
+        let count = r.length()?;
+        if capacity == 0 || count > capacity {
+            return Err("Invalid embedding cache capacity in snapshot".to_string());
+        }
+
+        let mut cache = EmbeddingCache {
+            entries: HashMap::with_capacity(count),
+            access_order: VecDeque::with_capacity(count),
+            capacity,
+        };
+        for _ in 0..count {
+            let fingerprint = r.string()?;
+            let access_count = r.u32()?;
+            let dims = r.length()?;
+            let mut embedding = Vec::with_capacity(dims);
+            for _ in 0..dims {
+                embedding.push(r.f32()?);
+            }
+            if cache.entries.contains_key(&fingerprint) {
+                return Err("Duplicate fingerprint in snapshot".to_string());
+            }
+            cache.access_order.push_back(fingerprint.clone());
+            cache.entries.insert(fingerprint, CacheEntry { embedding, access_count });
+        }
+
+        Ok(cache)
+    }
 }
 
 impl Default for EmbeddingCache {
@@ -0,0 +1,251 @@
+//! Packed columnar ingest format
+//!
+//! A batch of `n` records is sent as flat typed arrays instead of an array of
+//! objects. Strings (names, context entries, attribute keys/values,
+//! fingerprints, tag names) live once in a shared UTF-8 string table and are
+//! referenced by their position in it.
+//!
+//! | column           | type  | length        | contents                           |
+//! |------------------|-------|---------------|------------------------------------|
+//! | `ids`            | `u32` | `n`           | record ids                         |
+//! | `frameIds`       | `u16` | `n`           | frame ids                          |
+//! | `roles`          | `u8`  | `n`           | role codes (`ElementRole::code`)   |
+//! | `stateBits`      | `u32` | `n`           | state bitfields                    |
+//! | `rects`          | `i32` | `4n`          | `x, y, width, height` per record   |
+//! | `names`          | `u32` | `n`           | string ids                         |
+//! | `fingerprints`   | `u32` | `n`           | string ids                         |
+//! | `tagNames`       | `u32` | `n`           | string ids                         |
+//! | `contextOffsets` | `u32` | `n + 1`       | ranges into `contextIds`           |
+//! | `contextIds`     | `u32` | any           | string ids                         |
+//! | `attrOffsets`    | `u32` | `n + 1`       | ranges of pairs into `attrIds`     |
+//! | `attrIds`        | `u32` | 2 × pairs     | `key, value` string ids            |
+//! | `stringData`     | `u8`  | any           | concatenated UTF-8 strings         |
+//! | `stringOffsets`  | `u32` | strings + 1   | byte ranges into `stringData`      |
+
+use crate::store::{ColumnStore, Sym};
+use crate::types::{ElementRole, Rect};
+
+/// Columnar record batch, see the module docs for the layout
+#[derive(Debug, Clone, Default)]
+pub struct ColumnarBatch {
+    pub ids: Vec<u32>,
+    pub frame_ids: Vec<u16>,
+    pub roles: Vec<u8>,
+    pub state_bits: Vec<u32>,
+    pub rects: Vec<i32>,
+    pub names: Vec<u32>,
+    pub fingerprints: Vec<u32>,
+    pub tag_names: Vec<u32>,
+    pub context_offsets: Vec<u32>,
+    pub context_ids: Vec<u32>,
+    pub attr_offsets: Vec<u32>,
+    pub attr_ids: Vec<u32>,
+    pub string_data: Vec<u8>,
+    pub string_offsets: Vec<u32>,
+}
+
+impl ColumnarBatch {
+    /// Number of records in the batch
+    pub fn record_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check column lengths, offsets and references before decoding
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.record_count();
+        let per_record = [
+            ("frameIds", self.frame_ids.len()),
+            ("roles", self.roles.len()),
+            ("stateBits", self.state_bits.len()),
+            ("names", self.names.len()),
+            ("fingerprints", self.fingerprints.len()),
+            ("tagNames", self.tag_names.len()),
+        ];
+        for (column, len) in per_record {
+            if len != n {
+                return Err(format!("Column {} has {} entries, expected {}", column, len, n));
+            }
+        }
+        if self.rects.len() != n * 4 {
+            return Err(format!("Column rects has {} entries, expected {}", self.rects.len(), n * 4));
+        }
+        if let Some(&code) = self.roles.iter().find(|&&c| ElementRole::from_code(c).is_none()) {
+            return Err(format!("Unknown role code {}", code));
+        }
+
+        if !self.attr_ids.len().is_multiple_of(2) {
+            return Err("Column attrIds must contain key/value pairs".to_string());
+        }
+        check_offsets("contextOffsets", &self.context_offsets, n + 1, self.context_ids.len())?;
+        check_offsets("attrOffsets", &self.attr_offsets, n + 1, self.attr_ids.len() / 2)?;
+
+        if self.string_offsets.is_empty() {
+            return Err("Column stringOffsets must not be empty".to_string());
+        }
+        let string_count = self.string_offsets.len() - 1;
+        check_offsets("stringOffsets", &self.string_offsets, string_count + 1, self.string_data.len())?;
+        self.strings()?;
+
+        let refs = self.names.iter()
+            .chain(&self.fingerprints)
+            .chain(&self.tag_names)
+            .chain(&self.context_ids)
+            .chain(&self.attr_ids);
+        for &id in refs {
+            if id as usize >= string_count {
+                return Err(format!("String id {} out of range ({} strings)", id, string_count));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode the string table. The data is checked as UTF-8 in one pass and
+    /// every string must start and end on a character boundary.
+    pub fn strings(&self) -> Result<Vec<&str>, String> {
+        let data = std::str::from_utf8(&self.string_data)
+            .map_err(|_| "Invalid UTF-8 in string table".to_string())?;
+        self.string_offsets
+            .windows(2)
+            .map(|w| {
+                data.get(w[0] as usize..w[1] as usize)
+                    .ok_or_else(|| "Invalid UTF-8 in string table".to_string())
+            })
+            .collect()
+    }
+
+    /// Append the batch to column storage, interning the string table once.
+    /// The batch must have passed [`ColumnarBatch::validate`].
+    pub fn append_to(&self, store: &mut ColumnStore) -> Result<(), String> {
+        let syms: Vec<Sym> = self.strings()?
+            .into_iter()
+            .map(|s| store.strings.intern(s))
+            .collect();
+
+        let mut context = Vec::new();
+        let mut attrs = Vec::new();
+        for i in 0..self.record_count() {
+            let ctx_range = self.context_offsets[i] as usize..self.context_offsets[i + 1] as usize;
+            context.clear();
+            context.extend(self.context_ids[ctx_range].iter().map(|&id| syms[id as usize]));
+
+            let attr_range = self.attr_offsets[i] as usize * 2..self.attr_offsets[i + 1] as usize * 2;
+            attrs.clear();
+            attrs.extend(
+                self.attr_ids[attr_range]
+                    .chunks_exact(2)
+                    .map(|pair| (syms[pair[0] as usize], syms[pair[1] as usize])),
+            );
+
+            store.push_row(
+                self.ids[i],
+                self.frame_ids[i],
+                ElementRole::ALL[self.roles[i] as usize],
+                self.state_bits[i],
+                Rect {
+                    x: self.rects[i * 4],
+                    y: self.rects[i * 4 + 1],
+                    width: self.rects[i * 4 + 2],
+                    height: self.rects[i * 4 + 3],
+                },
+                [
+                    syms[self.names[i] as usize],
+                    syms[self.fingerprints[i] as usize],
+                    syms[self.tag_names[i] as usize],
+                ],
+                &context,
+                &attrs,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Offsets must have the expected length, start at 0, never decrease and end at `end`
+fn check_offsets(column: &str, offsets: &[u32], expected_len: usize, end: usize) -> Result<(), String> {
+    if offsets.len() != expected_len {
+        return Err(format!("Column {} has {} entries, expected {}", column, offsets.len(), expected_len));
+    }
+    if offsets[0] != 0 || offsets[offsets.len() - 1] as usize != end {
+        return Err(format!("Column {} must span 0..{}", column, end));
+    }
+    if offsets.windows(2).any(|w| w[0] > w[1]) {
+        return Err(format!("Column {} must be non-decreasing", column));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_batch() -> ColumnarBatch {
+        let strings = ["Save", "Settings", "data-testid", "save-btn", "fp-1", "button", "Search", "fp-2", "input"];
+        let mut string_data = Vec::new();
+        let mut string_offsets = vec![0];
+        for s in strings {
+            string_data.extend_from_slice(s.as_bytes());
+            string_offsets.push(string_data.len() as u32);
+        }
+
+        ColumnarBatch {
+            ids: vec![1, 2],
+            frame_ids: vec![0, 0],
+            roles: vec![ElementRole::Button.code(), ElementRole::Searchbox.code()],
+            state_bits: vec![3, 1],
+            rects: vec![0, 10, 80, 30, 100, 10, 200, 30],
+            names: vec![0, 6],
+            fingerprints: vec![4, 7],
+            tag_names: vec![5, 8],
+            context_offsets: vec![0, 1, 1],
+            context_ids: vec![1],
+            attr_offsets: vec![0, 1, 1],
+            attr_ids: vec![2, 3],
+            string_data,
+            string_offsets,
+        }
+    }
+
+    #[test]
+    fn test_append_batch() {
+        let batch = sample_batch();
+        batch.validate().unwrap();
+        let mut store = ColumnStore::new();
+        batch.append_to(&mut store).unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.name(0), "Save");
+        assert_eq!(store.context(0).collect::<Vec<_>>(), vec!["Settings"]);
+        assert_eq!(store.attr(0, "data-testid"), Some("save-btn"));
+        assert_eq!(store.roles[1], ElementRole::Searchbox);
+        assert_eq!(store.rects[1].x, 100);
+        assert_eq!(store.attrs(1).count(), 0);
+        assert_eq!(store.tag_name(1), "input");
+    }
+
+    #[test]
+    fn test_rejects_malformed_batch() {
+        let mut batch = sample_batch();
+        batch.names[1] = 99;
+        assert!(batch.validate().unwrap_err().contains("out of range"));
+
+        let mut batch = sample_batch();
+        batch.rects.pop();
+        assert!(batch.validate().unwrap_err().contains("rects"));
+
+        let mut batch = sample_batch();
+        batch.context_offsets = vec![0, 2, 1];
+        assert!(batch.validate().is_err());
+
+        let mut batch = sample_batch();
+        batch.string_data[0] = 0xff;
+        assert!(batch.validate().unwrap_err().contains("UTF-8"));
+
+        // An offset inside a multi-byte character
+        let mut batch = sample_batch();
+        batch.string_data[..2].copy_from_slice("é".as_bytes());
+        batch.string_offsets[1] = 1;
+        assert!(batch.validate().is_err());
+    }
+}
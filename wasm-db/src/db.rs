@@ -2,121 +2,224 @@
 
 use rustc_hash::FxHashMap;
 use crate::types::*;
-use crate::tokenizer::tokenize;
+use crate::tokenizer::{normalize, split_identifier, tokenize, Locale, StemLanguage, TextOptions};
 use crate::query::QueryExecutor;
+use crate::columnar::ColumnarBatch;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+use crate::store::ColumnStore;
+use crate::bitset::Bitset;
+use crate::synonyms::SynonymTable;
+use crate::trigram::TrigramIndex;
+use crate::lsh::LshIndex;
+use crate::phonetic;
+use crate::stem::StemIndex;
+
+/// Synonym profile loaded by new databases
+pub const DEFAULT_SYNONYM_PROFILE: &str = "de-en-ui";
+
+/// Attributes whose values are identifiers or labels worth indexing as words
+const IDENT_ATTRS: &[&str] = &[
+    "data-testid", "data-test", "data-test-id", "data-cy", "data-qa", "id", "name",
+    "aria-label", "aria-description", "aria-roledescription", "aria-placeholder",
+];
 
 /// Main UI Database with columnar storage and indices
 pub struct UiDatabase {
-    /// All records in columnar layout
-    records: Vec<NodeRecord>,
+    /// All records in columnar layout with interned strings
+    columns: ColumnStore,
     
     /// Role -> record indices
     role_index: FxHashMap<ElementRole, Vec<usize>>,
+
+    /// State flag bit -> records with that flag set
+    state_index: Vec<Bitset>,
     
     /// Token -> record indices (inverted index for name/context)
     token_index: FxHashMap<String, Vec<usize>>,
+
+    /// Stem -> record indices for names and context, when stemming is enabled
+    stem_index: StemIndex,
+
+    /// Phonetic code -> record indices, from the tokens of matchable names
+    phonetic_index: FxHashMap<String, Vec<usize>>,
     
     /// TestId -> record index (exact lookup)
     testid_index: FxHashMap<String, usize>,
+
+    /// Space-joined identifier words per record (testids, ids and aria
+    /// labels), a name filter's fallback
+    ident_texts: Vec<String>,
+
+    /// Trigrams of each record's matchable name
+    name_trigrams: TrigramIndex,
+
+    /// Trigrams of each record's identifier words
+    ident_trigrams: TrigramIndex,
+
+    /// Trigrams of context text, when enabled in the text options
+    context_trigrams: Option<TrigramIndex>,
+
+    /// MinHash signatures and LSH buckets of matchable names
+    name_lsh: LshIndex,
     
     /// Synonym mappings for multilingual support
-    synonyms: FxHashMap<String, Vec<String>>,
+    synonyms: SynonymTable,
+
+    /// Normalization shared by indexing and query matching
+    text_options: TextOptions,
 }
 
 impl UiDatabase {
     pub fn new() -> Self {
-        let mut db = UiDatabase {
-            records: Vec::new(),
+        UiDatabase {
+            columns: ColumnStore::new(),
             role_index: FxHashMap::default(),
+            state_index: vec![Bitset::empty(0); state_flags::COUNT],
             token_index: FxHashMap::default(),
+            stem_index: StemIndex::default(),
+            phonetic_index: FxHashMap::default(),
             testid_index: FxHashMap::default(),
-            synonyms: FxHashMap::default(),
-        };
-        db.init_synonyms();
-        db
-    }
-
-    /// Initialize common UI synonyms for multilingual matching
-    fn init_synonyms(&mut self) {
-        let synonym_groups = vec![
-            vec!["login", "sign in", "anmelden", "einloggen", "log in"],
-            vec!["logout", "sign out", "abmelden", "log out"],
-            vec!["submit", "send", "absenden", "senden", "ok", "confirm"],
-            vec!["cancel", "abbrechen", "close", "schließen"],
-            vec!["save", "speichern", "apply"],
-            vec!["delete", "remove", "löschen", "entfernen"],
-            vec!["edit", "bearbeiten", "modify", "ändern"],
-            vec!["search", "suchen", "find", "finden"],
-            vec!["next", "weiter", "continue", "fortfahren"],
-            vec!["back", "zurück", "previous"],
-            vec!["home", "startseite", "main"],
-            vec!["settings", "einstellungen", "preferences", "options"],
-            vec!["help", "hilfe", "support"],
-            vec!["profile", "profil", "account", "konto"],
-            vec!["password", "passwort", "kennwort"],
-            vec!["email", "e-mail", "mail"],
-            vec!["username", "benutzername", "user"],
-        ];
-
-        for group in synonym_groups {
-            for word in &group {
-                let others: Vec<String> = group.iter()
-                    .filter(|w| *w != word)
-                    .map(|w| w.to_string())
-                    .collect();
-                self.synonyms.insert(word.to_string(), others);
-            }
+            ident_texts: Vec::new(),
+            name_trigrams: TrigramIndex::default(),
+            ident_trigrams: TrigramIndex::default(),
+            context_trigrams: None,
+            name_lsh: LshIndex::default(),
+            synonyms: SynonymTable::with_profile(DEFAULT_SYNONYM_PROFILE)
+                .expect("default synonym profile exists"),
+            text_options: TextOptions::default(),
         }
     }
 
+    /// Change text normalization and re-index the stored records with it
+    pub fn set_text_options(&mut self, options: TextOptions) {
+        // Case sensitivity is chosen per filter
+        let options = TextOptions { case_sensitive: false, ..options };
+        if options == self.text_options {
+            return;
+        }
+        self.text_options = options;
+        self.synonyms.set_text_options(options);
+        self.role_index.clear();
+        self.token_index.clear();
+        self.testid_index.clear();
+        self.build_indices();
+    }
+
+    /// Get the active text normalization settings
+    pub fn text_options(&self) -> &TextOptions {
+        &self.text_options
+    }
+
+    /// Replace the synonym table with a built-in profile
+    pub fn set_synonym_profile(&mut self, profile: &str) -> Result<(), String> {
+        self.synonyms = SynonymTable::with_profile(profile)?;
+        self.synonyms.set_text_options(self.text_options);
+        Ok(())
+    }
+
+    /// Merge built-in profiles into the current synonym table
+    pub fn add_synonym_profile(&mut self, profile: &str) -> Result<(), String> {
+        self.synonyms.add_profile(profile)
+    }
+
+    /// Merge custom synonym groups given as JSON (see [`SynonymTable::load_json`])
+    pub fn load_synonyms(&mut self, groups_json: &str) -> Result<(), String> {
+        self.synonyms.load_json(groups_json)
+    }
+
+    /// Remove all synonyms
+    pub fn clear_synonyms(&mut self) {
+        self.synonyms.clear();
+    }
+
+    /// Get synonym table reference
+    pub fn synonyms(&self) -> &SynonymTable {
+        &self.synonyms
+    }
+
     /// Ingest records and build all indices
     pub fn ingest(&mut self, records: Vec<NodeRecord>) {
         self.reset();
-        self.records = records;
-        
-        for (idx, record) in self.records.iter().enumerate() {
+        for record in &records {
+            self.columns.push_record(record);
+        }
+        self.build_indices();
+    }
+
+    /// Ingest a packed columnar batch, skipping per-object deserialization
+    pub fn ingest_columnar(&mut self, batch: ColumnarBatch) -> Result<(), String> {
+        batch.validate()?;
+        self.reset();
+        batch.append_to(&mut self.columns)?;
+        self.build_indices();
+        Ok(())
+    }
+
+    /// Build role, state, token, testid and identifier indices over the stored columns
+    fn build_indices(&mut self) {
+        self.state_index = build_state_index(&self.columns);
+        self.ident_texts = build_ident_texts(&self.columns, &self.text_options);
+        (self.name_trigrams, self.ident_trigrams, self.context_trigrams) =
+            build_trigram_indices(&self.columns, &self.ident_texts, &self.text_options);
+        self.name_lsh = LshIndex::build((0..self.columns.len()).map(|idx| self.match_name(idx)), &self.text_options);
+        self.phonetic_index = phonetic::build_index((0..self.columns.len()).map(|idx| self.match_name(idx)), &self.text_options);
+        self.stem_index = build_stem_index(&self.columns, &self.ident_texts, &self.text_options);
+
+        let columns = &self.columns;
+        let testid_key = columns.strings.find("data-testid");
+
+        for idx in 0..columns.len() {
             // Role index
             self.role_index
-                .entry(record.role)
+                .entry(columns.roles[idx])
                 .or_default()
                 .push(idx);
             
             // Token index (name + context)
-            let mut tokens: Vec<String> = tokenize(&record.name);
-            for ctx in &record.context {
-                tokens.extend(tokenize(ctx));
+            let opts = &self.text_options;
+            let mut tokens: Vec<String> = tokenize(columns.name(idx), opts);
+            for ctx in columns.context(idx) {
+                tokens.extend(tokenize(ctx, opts));
             }
             
             for token in tokens {
                 let list = self.token_index.entry(token).or_default();
-                if !list.contains(&idx) {
+                if list.last() != Some(&idx) {
                     list.push(idx);
                 }
             }
             
             // TestId index
-            if let Some(testid) = record.attrs.get("data-testid") {
-                self.testid_index.insert(testid.clone(), idx);
+            if let Some(testid) = testid_key.and_then(|key| columns.attr_by_sym(idx, key)) {
+                self.testid_index.insert(testid.to_string(), idx);
             }
         }
     }
 
     /// Clear all data
     pub fn reset(&mut self) {
-        self.records.clear();
+        self.columns.clear();
         self.role_index.clear();
+        self.state_index = vec![Bitset::empty(0); state_flags::COUNT];
         self.token_index.clear();
+        self.stem_index = StemIndex::default();
+        self.phonetic_index.clear();
         self.testid_index.clear();
+        self.ident_texts.clear();
+        self.name_trigrams = TrigramIndex::default();
+        self.ident_trigrams = TrigramIndex::default();
+        self.context_trigrams = None;
+        self.name_lsh = LshIndex::default();
     }
 
     /// Get number of records
     pub fn size(&self) -> usize {
-        self.records.len()
+        self.columns.len()
     }
 
-    /// Get record by ID
-    pub fn get_record(&self, id: u32) -> Option<&NodeRecord> {
-        self.records.iter().find(|r| r.id == id)
+    /// Get record by ID, materialized from the columns
+    pub fn get_record(&self, id: u32) -> Option<NodeRecord> {
+        self.columns.index_of(id).map(|idx| self.columns.record(idx))
     }
 
     /// Execute a query and return ranked matches
@@ -127,9 +230,37 @@ impl UiDatabase {
         QueryExecutor::new(self, &self.synonyms).execute(&query)
     }
 
-    /// Get all records reference
-    pub fn records(&self) -> &[NodeRecord] {
-        &self.records
+    /// Up to `k` page words starting with `prefix`, most frequent first.
+    /// Only surface forms are suggested; stems live in the stem index.
+    /// Frequency is the number of records containing the word, counting only
+    /// records of `role` when given.
+    pub fn suggest(&self, prefix: &str, k: usize, role: Option<ElementRole>) -> Vec<TermFrequency> {
+        let prefix = normalize(prefix, &self.text_options);
+        let mut terms: Vec<TermFrequency> = self.token_index.iter()
+            .filter(|(token, _)| token.starts_with(&prefix))
+            .map(|(token, indices)| TermFrequency {
+                term: token.clone(),
+                frequency: match role {
+                    Some(role) => indices.iter().filter(|&&idx| self.columns.roles[idx] == role).count(),
+                    None => indices.len(),
+                },
+            })
+            .filter(|t| t.frequency > 0)
+            .collect();
+
+        terms.sort_by(|a, b| b.frequency.cmp(&a.frequency).then_with(|| a.term.cmp(&b.term)));
+        terms.truncate(k);
+        terms
+    }
+
+    /// Every page word with its record frequency, most frequent first
+    pub fn vocabulary(&self) -> Vec<TermFrequency> {
+        self.suggest("", usize::MAX, None)
+    }
+
+    /// Get column storage reference
+    pub fn columns(&self) -> &ColumnStore {
+        &self.columns
     }
 
     /// Get role index reference
@@ -137,15 +268,238 @@ impl UiDatabase {
         &self.role_index
     }
 
+    /// Get the per-flag state bitsets, indexed by bit position
+    pub fn state_index(&self) -> &[Bitset] {
+        &self.state_index
+    }
+
     /// Get token index reference  
     pub fn token_index(&self) -> &FxHashMap<String, Vec<usize>> {
         &self.token_index
     }
 
+    /// Get stem index reference
+    pub fn stem_index(&self) -> &StemIndex {
+        &self.stem_index
+    }
+
+    /// Get phonetic code index reference
+    pub fn phonetic_index(&self) -> &FxHashMap<String, Vec<usize>> {
+        &self.phonetic_index
+    }
+
     /// Get testid index reference
     pub fn testid_index(&self) -> &FxHashMap<String, usize> {
         &self.testid_index
     }
+
+    /// Text a name filter matches: the name, or the identifier words of
+    /// name-less records
+    pub fn match_name(&self, idx: usize) -> &str {
+        let name = self.columns.name(idx);
+        if name.trim().is_empty() {
+            &self.ident_texts[idx]
+        } else {
+            name
+        }
+    }
+
+    /// Identifier words of a record's testids, ids and aria labels
+    pub fn ident_text(&self, idx: usize) -> &str {
+        &self.ident_texts[idx]
+    }
+
+    /// Get the trigram index over matchable names
+    pub fn name_trigrams(&self) -> &TrigramIndex {
+        &self.name_trigrams
+    }
+
+    /// Get the trigram index over identifier words
+    pub fn ident_trigrams(&self) -> &TrigramIndex {
+        &self.ident_trigrams
+    }
+
+    /// Get the MinHash/LSH index over matchable names
+    pub fn name_lsh(&self) -> &LshIndex {
+        &self.name_lsh
+    }
+
+    /// Get the trigram index over context text, if enabled
+    pub fn context_trigrams(&self) -> Option<&TrigramIndex> {
+        self.context_trigrams.as_ref()
+    }
+
+    /// Serialize records, indices, synonyms and text options into a snapshot body.
+    /// Map entries are written in sorted key order so snapshots are deterministic.
+    pub fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        self.columns.write_snapshot(w);
+
+        let mut roles: Vec<_> = self.role_index.iter().collect();
+        roles.sort_by_key(|(role, _)| role.code());
+        w.put_len(roles.len());
+        for (role, indices) in roles {
+            w.put_u8(role.code());
+            w.put_sorted_indices(indices);
+        }
+
+        let mut tokens: Vec<_> = self.token_index.iter().collect();
+        tokens.sort_by(|a, b| a.0.cmp(b.0));
+        w.put_len(tokens.len());
+        for (token, indices) in tokens {
+            w.put_str(token);
+            w.put_sorted_indices(indices);
+        }
+
+        let mut testids: Vec<_> = self.testid_index.iter().collect();
+        testids.sort();
+        w.put_len(testids.len());
+        for (testid, idx) in testids {
+            w.put_str(testid);
+            w.put_len(*idx);
+        }
+
+        self.synonyms.write_snapshot(w);
+
+        w.put_u8(self.text_options.transliterate_german as u8);
+        w.put_u8(self.text_options.fold_diacritics as u8);
+        w.put_u8(self.text_options.stemming.map_or(0, |lang| lang.code() + 1));
+        w.put_u8(self.text_options.context_trigrams as u8);
+        w.put_u8(self.text_options.locale.code());
+    }
+
+    /// Rebuild a database from a snapshot body. Role, token and testid
+    /// indices are read back; the derived identifier, trigram, stem, LSH and
+    /// phonetic indices are rebuilt from the stored columns.
+    pub fn read_snapshot(r: &mut SnapshotReader) -> Result<Self, String> {
+        let columns = ColumnStore::read_snapshot(r)?;
+        let bound = columns.len();
+        let state_index = build_state_index(&columns);
+
+        let mut role_index = FxHashMap::default();
+        for _ in 0..r.length()? {
+            let role = ElementRole::from_code(r.u8()?)
+                .ok_or_else(|| "Unknown role in snapshot".to_string())?;
+            role_index.insert(role, r.sorted_indices(bound)?);
+        }
+
+        let mut token_index = FxHashMap::default();
+        for _ in 0..r.length()? {
+            let token = r.string()?;
+            token_index.insert(token, r.sorted_indices(bound)?);
+        }
+
+        let mut testid_index = FxHashMap::default();
+        for _ in 0..r.length()? {
+            let testid = r.string()?;
+            testid_index.insert(testid, r.index(bound)?);
+        }
+
+        let mut synonyms = SynonymTable::read_snapshot(r)?;
+
+        let text_options = TextOptions {
+            transliterate_german: r.u8()? != 0,
+            fold_diacritics: r.u8()? != 0,
+            stemming: match r.u8()? {
+                0 => None,
+                code => Some(StemLanguage::from_code(code - 1)
+                    .ok_or_else(|| "Unknown stemming language in snapshot".to_string())?),
+            },
+            context_trigrams: r.u8()? != 0,
+            locale: Locale::from_code(r.u8()?)
+                .ok_or_else(|| "Unknown locale in snapshot".to_string())?,
+            case_sensitive: false,
+        };
+        synonyms.set_text_options(text_options);
+
+        let ident_texts = build_ident_texts(&columns, &text_options);
+        let (name_trigrams, ident_trigrams, context_trigrams) =
+            build_trigram_indices(&columns, &ident_texts, &text_options);
+        let stem_index = build_stem_index(&columns, &ident_texts, &text_options);
+
+        let mut db = UiDatabase {
+            columns,
+            role_index,
+            state_index,
+            token_index,
+            stem_index,
+            phonetic_index: FxHashMap::default(),
+            testid_index,
+            ident_texts,
+            name_trigrams,
+            ident_trigrams,
+            context_trigrams,
+            name_lsh: LshIndex::default(),
+            synonyms,
+            text_options,
+        };
+        db.name_lsh = LshIndex::build((0..db.columns.len()).map(|idx| db.match_name(idx)), &db.text_options);
+        db.phonetic_index = phonetic::build_index((0..db.columns.len()).map(|idx| db.match_name(idx)), &db.text_options);
+        Ok(db)
+    }
+}
+
+/// Transpose the state bits column into one bitset per flag
+fn build_state_index(columns: &ColumnStore) -> Vec<Bitset> {
+    let mut index = vec![Bitset::empty(columns.len()); state_flags::COUNT];
+    for (idx, &bits) in columns.state_bits.iter().enumerate() {
+        let mut remaining = bits & ((1 << state_flags::COUNT) - 1);
+        while remaining != 0 {
+            let bit = remaining.trailing_zeros() as usize;
+            remaining &= remaining - 1;
+            index[bit].insert(idx);
+        }
+    }
+    index
+}
+
+/// Split identifier attributes into words, kept apart from name and context
+/// tokens
+fn build_ident_texts(columns: &ColumnStore, opts: &TextOptions) -> Vec<String> {
+    let keys: Vec<_> = IDENT_ATTRS.iter()
+        .filter_map(|key| columns.strings.find(key))
+        .collect();
+    let mut texts = Vec::with_capacity(columns.len());
+
+    for idx in 0..columns.len() {
+        let mut words = Vec::new();
+        for &(key, value) in columns.attr_syms(idx) {
+            if keys.contains(&key) {
+                words.extend(split_identifier(columns.strings.get(value), opts));
+            }
+        }
+        texts.push(words.join(" "));
+    }
+
+    texts
+}
+
+/// Trigram indices over matchable names, identifier words and, if enabled,
+/// context text
+fn build_trigram_indices(
+    columns: &ColumnStore,
+    ident_texts: &[String],
+    opts: &TextOptions,
+) -> (TrigramIndex, TrigramIndex, Option<TrigramIndex>) {
+    let name_trigrams = TrigramIndex::build(match_names(columns, ident_texts), opts);
+    let ident_trigrams = TrigramIndex::build(ident_texts.iter().map(String::as_str), opts);
+    let context_trigrams = opts.context_trigrams.then(|| {
+        TrigramIndex::build((0..columns.len()).map(|idx| columns.context_text(idx)), opts)
+    });
+    (name_trigrams, ident_trigrams, context_trigrams)
+}
+
+/// Stems of matchable names and context text
+fn build_stem_index(columns: &ColumnStore, ident_texts: &[String], opts: &TextOptions) -> StemIndex {
+    let contexts = (0..columns.len()).map(|idx| columns.context_text(idx));
+    StemIndex::build(match_names(columns, ident_texts), contexts, opts)
+}
+
+/// Matchable name of each record, as [`UiDatabase::match_name`] picks it
+fn match_names<'c>(columns: &'c ColumnStore, ident_texts: &'c [String]) -> impl Iterator<Item = &'c str> {
+    (0..columns.len()).map(move |idx| {
+        let name = columns.name(idx);
+        if name.trim().is_empty() { ident_texts[idx].as_str() } else { name }
+    })
 }
 
 impl Default for UiDatabase {
@@ -153,3 +507,600 @@ impl Default for UiDatabase {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::types::state_flags::{ENABLED, VISIBLE};
+
+    fn record(id: u32, role: ElementRole, name: &str, state_bits: u32) -> NodeRecord {
+        NodeRecord {
+            id,
+            frame_id: 0,
+            role,
+            name: name.to_string(),
+            state_bits,
+            attrs: HashMap::new(),
+            context: vec![],
+            rect: Rect { x: 0, y: id as i32 * 40, width: 100, height: 30 },
+            fingerprint: format!("fp-{}", id),
+            tag_name: "button".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_multi_clause_query() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Save", VISIBLE | ENABLED),
+            record(2, ElementRole::Button, "Save draft", VISIBLE),
+            record(3, ElementRole::Link, "Save", VISIBLE | ENABLED),
+            record(4, ElementRole::Button, "Cancel", VISIBLE | ENABLED),
+        ]);
+
+        let result = db.query(r#"{"where": [
+            {"role": "button"},
+            {"state": {"visible": true, "enabled": true}},
+            {"name": {"match": "contains", "value": "save"}}
+        ]}"#).unwrap();
+        let ids: Vec<u32> = result.matches.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1]);
+
+        let result = db.query(r#"{"where": [{"state": {"enabled": false}}]}"#).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.matches[0].id, 2);
+    }
+
+    #[test]
+    fn test_rejected_batch_keeps_records() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![record(1, ElementRole::Button, "Save", VISIBLE)]);
+
+        let batch = ColumnarBatch {
+            ids: vec![2],
+            frame_ids: vec![0],
+            roles: vec![ElementRole::Button.code()],
+            state_bits: vec![VISIBLE],
+            rects: vec![0, 0, 100, 30],
+            names: vec![0],
+            fingerprints: vec![0],
+            tag_names: vec![0],
+            context_offsets: vec![0, 0],
+            context_ids: vec![],
+            attr_offsets: vec![0, 0],
+            attr_ids: vec![],
+            string_data: vec![0xff, 0xfe],
+            string_offsets: vec![0, 2],
+        };
+        assert!(db.ingest_columnar(batch).unwrap_err().contains("UTF-8"));
+        assert_eq!(db.size(), 1);
+        assert_eq!(db.get_record(1).unwrap().name, "Save");
+    }
+
+    #[test]
+    fn test_synonym_matches_are_weighted() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "OK", VISIBLE),
+            record(2, ElementRole::Button, "Absenden", VISIBLE),
+            record(3, ElementRole::Button, "Submit", VISIBLE),
+        ]);
+
+        let result = db.query(r#"{"where": [{"role": "button"}, {"name": {"match": "exact", "value": "submit"}}]}"#).unwrap();
+        let ids: Vec<u32> = result.matches.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+
+        let used: Vec<(&str, f64)> = result.explain.synonyms_used.iter()
+            .map(|u| (u.synonym.as_str(), u.weight))
+            .collect();
+        assert_eq!(used, vec![("absenden", 0.9), ("ok", 0.6)]);
+    }
+
+    #[test]
+    fn test_synonyms_match_folded_spellings() {
+        let mut db = UiDatabase::new();
+        db.add_synonym_profile("fr-ui").unwrap();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Delete", VISIBLE),
+            record(2, ElementRole::Button, "Logout", VISIBLE),
+        ]);
+
+        let ids = |db: &UiDatabase, value: &str| {
+            let query = format!(r#"{{"where": [{{"name": {{"match": "exact", "value": "{}"}}}}]}}"#, value);
+            db.query(&query).unwrap().matches.iter().map(|m| m.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&db, "Löschen"), vec![1]);
+        assert_eq!(ids(&db, "Loeschen"), vec![1]);
+        assert_eq!(ids(&db, "deconnexion"), vec![2]);
+
+        // Re-keyed when the normalization changes
+        db.set_text_options(TextOptions { transliterate_german: false, ..TextOptions::default() });
+        assert_eq!(ids(&db, "Löschen"), vec![1]);
+        assert!(ids(&db, "Loeschen").is_empty());
+    }
+
+    #[test]
+    fn test_phrase_and_context_synonyms() {
+        let mut form = record(3, ElementRole::Textbox, "Password", VISIBLE);
+        form.context = vec!["Anmelden".to_string()];
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Delete account", VISIBLE),
+            record(2, ElementRole::Heading, "Einloggen", VISIBLE),
+            form,
+        ]);
+
+        // A single token inside the phrase is replaced by its synonym
+        let result = db.query(r#"{"where": [{"name": {"match": "contains", "value": "remove account"}}]}"#).unwrap();
+        assert_eq!(result.matches[0].id, 1);
+        assert_eq!(result.explain.synonyms_used[0].term, "remove");
+        assert_eq!(result.explain.synonyms_used[0].synonym, "delete account");
+
+        let result = db.query(r#"{"where": [{"in_context": {"match": "contains", "value": "sign in"}}]}"#).unwrap();
+        assert_eq!(result.matches.iter().map(|m| m.id).collect::<Vec<_>>(), vec![3]);
+
+        // Multi-word anchor text resolves through its synonym
+        let result = db.query(r#"{"where": [{"role": "textbox"}, {"near": {"text": "log in", "radius": 100}}]}"#).unwrap();
+        assert_eq!(result.total, 1);
+    }
+
+    #[test]
+    fn test_stem_fallback_uses_stem_index() {
+        let mut profile = record(3, ElementRole::Link, "Profile", VISIBLE);
+        profile.context = vec!["Saving preferences".to_string()];
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Deleting items", VISIBLE),
+            record(2, ElementRole::Button, "Delete", VISIBLE),
+            profile,
+        ]);
+
+        let ids = |db: &UiDatabase, query: &str| {
+            let mut ids: Vec<u32> = db.query(query).unwrap().matches.iter().map(|m| m.id).collect();
+            ids.sort();
+            ids
+        };
+        let deleted = r#"{"where": [{"name": {"match": "contains", "value": "deleted"}}]}"#;
+        assert!(ids(&db, deleted).is_empty());
+
+        db.set_text_options(TextOptions { stemming: Some(StemLanguage::En), ..TextOptions::default() });
+        assert_eq!(ids(&db, deleted), vec![1, 2]);
+        assert_eq!(ids(&db, r#"{"where": [{"in_context": {"match": "contains", "value": "saved"}}]}"#), vec![3]);
+        // Stems are kept out of the surface-word index
+        assert!(db.token_index().get("delet").is_none());
+    }
+
+    #[test]
+    fn test_nameless_button_found_by_identifiers() {
+        let mut icon = record(1, ElementRole::Button, "", VISIBLE);
+        icon.attrs.insert("data-testid".to_string(), "submitOrderBtn".to_string());
+        let mut input = record(2, ElementRole::Textbox, "E-Mail", VISIBLE);
+        input.attrs.insert("id".to_string(), "checkout-form__email_input".to_string());
+        let mut labelled = record(3, ElementRole::Button, "Submit order", VISIBLE);
+        labelled.attrs.insert("data-testid".to_string(), "order-submit".to_string());
+        let mut db = UiDatabase::new();
+        db.ingest(vec![icon, input, labelled]);
+
+        assert_eq!(db.match_name(0), "submit order btn");
+        // Named records keep their name
+        assert_eq!(db.match_name(1), "E-Mail");
+
+        let result = db.query(r#"{"where": [{"name": {"match": "contains", "value": "submit order"}}]}"#).unwrap();
+        let ids: Vec<u32> = result.matches.iter().map(|m| m.id).collect();
+        // The labelled button outranks the identifier-only match
+        assert_eq!(ids, vec![3, 1]);
+    }
+
+    #[test]
+    fn test_named_record_found_by_identifier_words() {
+        let mut send = record(1, ElementRole::Button, "Send", VISIBLE);
+        send.attrs.insert("data-testid".to_string(), "submitOrderBtn".to_string());
+        let mut order = record(2, ElementRole::Button, "Order now", VISIBLE);
+        order.attrs.insert("data-testid".to_string(), "cta".to_string());
+        let mut db = UiDatabase::new();
+        db.ingest(vec![send, order, record(3, ElementRole::Button, "Cancel", VISIBLE)]);
+
+        let ids = |query: &str| db.query(query).unwrap().matches.iter().map(|m| m.id).collect::<Vec<u32>>();
+        assert_eq!(ids(r#"{"where": [{"attr": {"name": "data-testid", "value": "order", "match": "word"}}]}"#), vec![1]);
+        // The name match outranks the identifier fallback
+        assert_eq!(ids(r#"{"where": [{"name": {"match": "contains", "value": "order"}}]}"#), vec![2, 1]);
+        // Exact testid lookups still need the whole value
+        assert!(ids(r#"{"where": [{"attr": {"name": "data-testid", "value": "order"}}]}"#).is_empty());
+    }
+
+    #[test]
+    fn test_lsh_name_match() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Sign-In", VISIBLE),
+            record(2, ElementRole::Button, "Sign in now", VISIBLE),
+            record(3, ElementRole::Button, "Single", VISIBLE),
+            record(4, ElementRole::Button, "Settings", VISIBLE),
+        ]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "lsh", "value": "sign in"}}]}"#).unwrap();
+        let mut ids: Vec<u32> = result.matches.iter().map(|m| m.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(result.explain.filters_applied, vec!["name(lsh:sign in)"]);
+    }
+
+    #[test]
+    fn test_phonetic_name_match() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Sign in", VISIBLE),
+            record(2, ElementRole::Button, "Logout", VISIBLE),
+            record(3, ElementRole::Link, "Herr Meyer", VISIBLE),
+            record(4, ElementRole::Button, "Settings", VISIBLE),
+        ]);
+
+        let ids = |value: &str| {
+            let query = format!(r#"{{"where": [{{"name": {{"match": "phonetic", "value": "{}"}}}}]}}"#, value);
+            let mut ids: Vec<u32> = db.query(&query).unwrap().matches.iter().map(|m| m.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("Sign Inn"), vec![1]);
+        assert_eq!(ids("lock out"), vec![2]);
+        assert_eq!(ids("Maier"), vec![3]);
+        assert!(ids("Zebra").is_empty());
+    }
+
+    #[test]
+    fn test_word_and_glob_filters() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Save", VISIBLE),
+            record(2, ElementRole::Button, "Unsaved changes", VISIBLE),
+            record(3, ElementRole::Button, "Save all changes", VISIBLE),
+        ]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "word", "value": "save"}}]}"#).unwrap();
+        let ids: Vec<u32> = result.matches.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(result.explain.filters_applied, vec!["name(word:save)"]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "glob", "value": "*changes"}}]}"#).unwrap();
+        let mut ids: Vec<u32> = result.matches.iter().map(|m| m.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![2, 3]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "starts_with", "value": "save"}}]}"#).unwrap();
+        assert_eq!(result.matches.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1, 3]);
+
+        // Punctuation between the words doesn't hide them from the trigram shortlist
+        db.ingest(vec![record(4, ElementRole::Button, "Save, changes", VISIBLE)]);
+        let result = db.query(r#"{"where": [{"name": {"match": "word", "value": "save changes"}}]}"#).unwrap();
+        assert_eq!(result.total, 1);
+
+        // Single-character words are matched, not skipped
+        db.ingest(vec![
+            record(5, ElementRole::Button, "Plan A", VISIBLE),
+            record(6, ElementRole::Button, "Plan B", VISIBLE),
+            record(7, ElementRole::Button, "Item 7", VISIBLE),
+        ]);
+        let ids = |query: &str| db.query(query).unwrap().matches.iter().map(|m| m.id).collect::<Vec<u32>>();
+        assert_eq!(ids(r#"{"where": [{"name": {"match": "word", "value": "Plan A"}}]}"#), vec![5]);
+        assert_eq!(ids(r#"{"where": [{"name": {"match": "word", "value": "7"}}]}"#), vec![7]);
+    }
+
+    #[test]
+    fn test_case_sensitive_filters() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Ship to US", VISIBLE),
+            record(2, ElementRole::Button, "Contact us", VISIBLE),
+        ]);
+
+        let ids = |query: &str| db.query(query).unwrap().matches.iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(r#"{"where": [{"name": {"match": "word", "value": "US", "case_sensitive": true}}]}"#), vec![1]);
+        assert_eq!(ids(r#"{"where": [{"name": {"match": "word", "value": "us", "case_sensitive": true}}]}"#), vec![2]);
+        assert_eq!(ids(r#"{"where": [{"name": {"match": "word", "value": "US"}}]}"#).len(), 2);
+    }
+
+    #[test]
+    fn test_suggest_and_vocabulary() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Save", VISIBLE),
+            record(2, ElementRole::Button, "Save all", VISIBLE),
+            record(3, ElementRole::Link, "Saved items", VISIBLE),
+            record(4, ElementRole::Link, "Search", VISIBLE),
+        ]);
+
+        let terms = |list: Vec<TermFrequency>| list.into_iter().map(|t| (t.term, t.frequency)).collect::<Vec<_>>();
+        assert_eq!(terms(db.suggest("Sa", 10, None)), vec![("save".into(), 2), ("saved".into(), 1)]);
+        assert_eq!(terms(db.suggest("s", 1, None)), vec![("save".into(), 2)]);
+        assert_eq!(terms(db.suggest("s", 10, Some(ElementRole::Link))), vec![("saved".into(), 1), ("search".into(), 1)]);
+
+        let vocabulary = db.vocabulary();
+        assert_eq!(vocabulary.len(), 5);
+        assert_eq!(vocabulary[0].term, "save");
+
+        // Only words on the page, never their stems
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Delete", VISIBLE),
+            record(2, ElementRole::Button, "Settings", VISIBLE),
+        ]);
+        db.set_text_options(TextOptions { stemming: Some(StemLanguage::En), ..TextOptions::default() });
+        assert_eq!(terms(db.vocabulary()), vec![("delete".into(), 1), ("settings".into(), 1)]);
+        assert_eq!(terms(db.suggest("se", 10, None)), vec![("settings".into(), 1)]);
+    }
+
+    #[test]
+    fn test_spelling_suggestions_and_autocorrect() {
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Login", VISIBLE),
+            record(2, ElementRole::Link, "Logout", VISIBLE),
+            record(3, ElementRole::Button, "Settings", VISIBLE),
+        ]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "lgoin"}}]}"#).unwrap();
+        assert_eq!(result.total, 0);
+        assert!(!result.explain.autocorrected);
+        assert_eq!(result.explain.spelling_suggestions.len(), 1);
+        assert_eq!(result.explain.spelling_suggestions[0].token, "lgoin");
+        assert_eq!(result.explain.spelling_suggestions[0].suggestions, vec!["login"]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "Settigns"}}], "autocorrect": true}"#).unwrap();
+        assert!(result.explain.autocorrected);
+        assert_eq!(result.matches.iter().map(|m| m.id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(result.explain.filters_applied, vec!["name(exact:settings)"]);
+
+        // Known words and matching queries get no suggestions
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "login"}}]}"#).unwrap();
+        assert!(result.explain.spelling_suggestions.is_empty());
+
+        // Suggestions are page words, not the stems indexed next to them
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Delete", VISIBLE),
+            record(2, ElementRole::Button, "Settings", VISIBLE),
+        ]);
+        db.set_text_options(TextOptions { stemming: Some(StemLanguage::En), ..TextOptions::default() });
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "delte"}}], "autocorrect": true}"#).unwrap();
+        assert_eq!(result.explain.spelling_suggestions[0].suggestions, vec!["delete"]);
+        assert!(result.explain.autocorrected);
+        assert_eq!(result.matches.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_relax_weakens_clauses_in_order() {
+        let mut db = UiDatabase::new();
+        let mut save = record(1, ElementRole::Button, "Save changes", VISIBLE);
+        save.context = vec!["Profile".to_string()];
+        db.ingest(vec![save]);
+
+        let query = |relax: bool| db.query(&format!(
+            r#"{{"where": [{{"name": {{"match": "exact", "value": "save"}}}}, {{"in_context": {{"match": "exact", "value": "Profile"}}}}], "relax": {}}}"#,
+            relax,
+        )).unwrap();
+        assert_eq!(query(false).total, 0);
+
+        let result = query(true);
+        assert_eq!(result.total, 1);
+        assert_eq!(result.explain.relaxations, vec!["name(exact->contains:save)", "context(exact->contains:Profile)"]);
+
+        let result = db.query(r#"{"where": [{"name": {"match": "contains", "value": "save"}}, {"in_context": {"match": "contains", "value": "Billing"}}, {"state": {"enabled": true}}], "relax": true}"#).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.explain.relaxations, vec![
+            "name(contains->fuzzy:save)",
+            "context(contains->fuzzy:Billing)",
+            "drop context(Billing)",
+            "drop state",
+        ]);
+    }
+
+    #[test]
+    fn test_ambiguous_matches_get_disambiguation_hints() {
+        let mut records = Vec::new();
+        for (i, (section, person)) in [("Section A", "Alice"), ("Section B", "Bob"), ("Section C", "")].iter().enumerate() {
+            let y = i as i32 * 100;
+            let mut heading = record(10 + i as u32, ElementRole::Heading, section, VISIBLE);
+            heading.rect.y = y;
+            let mut edit = record(1 + i as u32, ElementRole::Button, "Edit", VISIBLE);
+            edit.rect.y = y + 40;
+            edit.attrs.insert("data-testid".to_string(), format!("edit-{}", i + 1));
+            if !person.is_empty() {
+                edit.context = vec![person.to_string()];
+            }
+            records.extend([heading, edit]);
+        }
+        let mut db = UiDatabase::new();
+        db.ingest(records);
+
+        let result = db.query(r#"{"where": [{"role": "button"}, {"name": {"match": "exact", "value": "Edit"}}]}"#).unwrap();
+        let ambiguity = result.ambiguity.expect("three identical buttons are ambiguous");
+        assert_eq!(ambiguity.score_gap, 0.0);
+        assert_eq!(ambiguity.candidates.len(), 3);
+
+        let first = ambiguity.candidates.iter().find(|c| c.id == 1).unwrap();
+        assert_eq!(first.context, vec!["Alice"]);
+        assert_eq!(first.heading.as_deref(), Some("Section A"));
+        assert_eq!(first.position, 1);
+        assert_eq!(first.testid.as_deref(), Some("edit-1"));
+        assert_eq!(first.suggested_where.len(), 3);
+        assert!(matches!(&first.suggested_where[0], WhereClause::Attr { attr } if attr.value == "edit-1"));
+
+        let last = ambiguity.candidates.iter().find(|c| c.id == 3).unwrap();
+        assert!(last.context.is_empty());
+        assert_eq!(last.position, 3);
+        assert!(matches!(&last.suggested_where[1], WhereClause::Near { near } if near.target_id == Some(12)));
+
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "Section A"}}]}"#).unwrap();
+        assert!(result.ambiguity.is_none());
+    }
+
+    #[test]
+    fn test_heading_hint_prefers_own_column() {
+        let mut orders = record(10, ElementRole::Heading, "Orders", VISIBLE);
+        orders.rect.y = 0;
+        let mut invoices = record(11, ElementRole::Heading, "Invoices", VISIBLE);
+        invoices.rect = Rect { x: 400, y: 100, width: 100, height: 30 };
+        let mut left = record(1, ElementRole::Button, "Edit", VISIBLE);
+        left.rect.y = 200;
+        let mut right = record(2, ElementRole::Button, "Edit", VISIBLE);
+        right.rect = Rect { x: 400, y: 200, width: 100, height: 30 };
+        let mut db = UiDatabase::new();
+        db.ingest(vec![orders, invoices, left, right]);
+
+        let result = db.query(r#"{"where": [{"role": "button"}]}"#).unwrap();
+        let ambiguity = result.ambiguity.expect("two identical buttons are ambiguous");
+        let heading = |id: u32| ambiguity.candidates.iter().find(|c| c.id == id).unwrap().heading.clone();
+        // The closer heading of the right column doesn't win over the left one's own
+        assert_eq!(heading(1).as_deref(), Some("Orders"));
+        assert_eq!(heading(2).as_deref(), Some("Invoices"));
+    }
+
+    #[test]
+    fn test_hints_isolate_from_unreported_ties() {
+        let mut db = UiDatabase::new();
+        db.ingest((1..=8).map(|i| {
+            let mut edit = record(i, ElementRole::Button, "Edit", VISIBLE);
+            edit.rect.y = i as i32 * 30;
+            // The first row shares its context with one beyond the reported five
+            edit.context = vec![if i == 1 || i == 7 { "Invoices".to_string() } else { format!("Row {}", i) }];
+            edit
+        }).collect());
+
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "Edit"}}]}"#).unwrap();
+        let ambiguity = result.ambiguity.expect("eight identical buttons are ambiguous");
+        assert_eq!(ambiguity.candidates.len(), 5);
+
+        let first = ambiguity.candidates.iter().find(|c| c.id == 1).unwrap();
+        assert!(first.context.is_empty());
+        assert!(first.suggested_where.is_empty());
+
+        let second = ambiguity.candidates.iter().find(|c| c.id == 2).unwrap();
+        assert_eq!(second.context, vec!["Row 2"]);
+        assert_eq!(second.suggested_where.len(), 1);
+    }
+
+    #[test]
+    fn test_ambiguity_ignores_order_and_page() {
+        let mut draft = record(3, ElementRole::Button, "Save draft", VISIBLE);
+        draft.rect.y = 500;
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            record(1, ElementRole::Button, "Save", VISIBLE),
+            record(2, ElementRole::Button, "Save", VISIBLE),
+            draft,
+        ]);
+
+        let tied = |extra: &str| {
+            let query = format!(
+                r#"{{"where": [{{"role": "button"}}, {{"name": {{"match": "contains", "value": "save"}}}}]{}}}"#,
+                extra,
+            );
+            let ambiguity = db.query(&query).unwrap().ambiguity.expect("two identical buttons are ambiguous");
+            let mut ids: Vec<u32> = ambiguity.candidates.iter().map(|c| c.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(tied(""), vec![1, 2]);
+        // The lower-scoring draft button listed first doesn't join the tie
+        assert_eq!(tied(r#", "order_by": [{"field": "y", "direction": "desc"}]"#), vec![1, 2]);
+        // A page holding only one of the tied buttons still reports both
+        assert_eq!(tied(r#", "offset": 1, "limit": 1"#), vec![1, 2]);
+        assert_eq!(tied(r#", "limit": 1"#), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_match_highlights() {
+        let mut db = UiDatabase::new();
+        let mut save = record(1, ElementRole::Button, "Speichern", VISIBLE);
+        save.context = vec!["Profil".to_string(), "Rechnungsadresse 🏠".to_string()];
+        save.attrs.insert("data-testid".to_string(), "save-address".to_string());
+        db.ingest(vec![save]);
+
+        let result = db.query(r#"{"where": [
+            {"name": {"match": "contains", "value": "save"}},
+            {"in_context": {"match": "contains", "value": "adresse"}},
+            {"attr": {"name": "data-testid", "value": "address", "match": "ends_with"}}
+        ]}"#).unwrap();
+        let highlights = &result.matches[0].highlights;
+        assert_eq!(highlights.len(), 3);
+
+        assert_eq!(highlights[0].field, "name");
+        assert_eq!((highlights[0].start, highlights[0].end), (0, 9));
+        assert_eq!(highlights[0].term, "speichern");
+
+        assert_eq!(highlights[1].field, "context");
+        assert_eq!(highlights[1].index, Some(1));
+        assert_eq!((highlights[1].start, highlights[1].end), (9, 16));
+
+        assert_eq!(highlights[2].attr.as_deref(), Some("data-testid"));
+        assert_eq!((highlights[2].start, highlights[2].end), (5, 12));
+    }
+
+    #[test]
+    fn test_min_score_and_score_modes() {
+        let mut tagged = record(1, ElementRole::Button, "Save", VISIBLE);
+        tagged.attrs.insert("data-testid".to_string(), "save".to_string());
+        let mut db = UiDatabase::new();
+        db.ingest(vec![
+            tagged,
+            record(2, ElementRole::Button, "Save", VISIBLE),
+            record(3, ElementRole::Button, "Save draft", VISIBLE),
+        ]);
+
+        let query = |extra: &str| db.query(&format!(
+            r#"{{"where": [{{"role": "button"}}, {{"state": {{"visible": true}}}}, {{"name": {{"match": "contains", "value": "save"}}}}]{}}}"#,
+            extra,
+        )).unwrap();
+        let scores = |result: &QueryResult| result.matches.iter().map(|m| (m.id, m.score)).collect::<Vec<_>>();
+
+        assert_eq!(scores(&query("")), vec![(1, 1.0), (2, 1.0), (3, 0.97)]);
+        assert_eq!(scores(&query(r#", "score_mode": "raw""#)), vec![(1, 1.1), (2, 1.0), (3, 0.97)]);
+
+        let softmax = query(r#", "score_mode": "softmax""#);
+        let total: f64 = softmax.matches.iter().map(|m| m.score).sum();
+        assert!((total - 1.0).abs() < 0.02);
+        assert_eq!(softmax.matches[0].id, 1);
+        assert!(softmax.matches[0].score > 0.8);
+
+        assert_eq!(query(r#", "min_score": 0.99"#).total, 2);
+        let confident = query(r#", "score_mode": "softmax", "min_score": 0.5"#);
+        assert_eq!(scores(&confident).len(), 1);
+        assert_eq!(confident.total, 1);
+        assert!(confident.explain.filters_applied.contains(&"min_score(0.5)".to_string()));
+    }
+
+    #[test]
+    fn test_pagination_matches_full_ranking() {
+        let names = ["Save", "Save all", "Saved items", "Autosave", "Cancel", "Safe mode", "Save as"];
+        let mut db = UiDatabase::new();
+        db.ingest(
+            names.iter()
+                .enumerate()
+                .map(|(i, name)| record(i as u32, ElementRole::Generic, name, VISIBLE))
+                .collect(),
+        );
+
+        let full = db.query(r#"{"where": [{"role": "generic"}, {"name": {"match": "fuzzy", "value": "save"}}], "limit": 100}"#).unwrap();
+        let page = db.query(r#"{"where": [{"role": "generic"}, {"name": {"match": "fuzzy", "value": "save"}}], "limit": 2, "offset": 1}"#).unwrap();
+
+        assert_eq!(page.total, full.total);
+        let full_ids: Vec<u32> = full.matches.iter().map(|m| m.id).collect();
+        let page_ids: Vec<u32> = page.matches.iter().map(|m| m.id).collect();
+        assert_eq!(page_ids, full_ids[1..3].to_vec());
+        assert!(full.matches.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let by_y = db.query(r#"{"where": [{"role": "generic"}], "order_by": [{"field": "y", "direction": "desc"}], "limit": 2}"#).unwrap();
+        let by_y_ids: Vec<u32> = by_y.matches.iter().map(|m| m.id).collect();
+        assert_eq!(by_y_ids, vec![6, 5]);
+    }
+
+    #[test]
+    fn test_huge_limit() {
+        let mut db = UiDatabase::new();
+        db.ingest((0..3).map(|i| record(i, ElementRole::Button, "Save", VISIBLE)).collect());
+
+        let result = db.query(r#"{"where": [{"role": "button"}], "limit": 4000000000}"#).unwrap();
+        assert_eq!(result.matches.len(), 3);
+        let query = format!(r#"{{"where": [{{"role": "button"}}], "offset": 1, "limit": {}}}"#, usize::MAX);
+        assert_eq!(db.query(&query).unwrap().matches.len(), 2);
+    }
+}
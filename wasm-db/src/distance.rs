@@ -0,0 +1,148 @@
+//! Edit distances for typo-tolerant matching
+
+use rustc_hash::FxHashMap;
+
+/// Levenshtein distance if it is at most `max`, otherwise `None`.
+/// Uses Myers' bit-parallel algorithm when the shorter string fits in a
+/// 64-bit word and stops as soon as the bound can no longer be met.
+pub fn levenshtein_bounded(a: &str, b: &str, max: usize) -> Option<usize> {
+    let mut a: Vec<char> = a.chars().collect();
+    let mut b: Vec<char> = b.chars().collect();
+    if a.len() > b.len() {
+        std::mem::swap(&mut a, &mut b);
+    }
+    if b.len() - a.len() > max {
+        return None;
+    }
+    if a.is_empty() {
+        return Some(b.len());
+    }
+    if a.len() > 64 {
+        return levenshtein_rows(&a, &b).filter(|&d| d <= max);
+    }
+
+    // Bit i of peq[c] is set where a[i] == c
+    let mut peq: FxHashMap<char, u64> = FxHashMap::default();
+    for (i, &c) in a.iter().enumerate() {
+        *peq.entry(c).or_default() |= 1 << i;
+    }
+
+    let m = a.len();
+    let last = 1u64 << (m - 1);
+    let mut pv = if m == 64 { u64::MAX } else { (1u64 << m) - 1 };
+    let mut mv = 0u64;
+    let mut score = m;
+
+    for (j, c) in b.iter().enumerate() {
+        let eq = peq.get(c).copied().unwrap_or(0);
+        let xv = eq | mv;
+        let xh = ((eq & pv).wrapping_add(pv) ^ pv) | eq;
+        let mut ph = mv | !(xh | pv);
+        let mut mh = pv & xh;
+        if ph & last != 0 {
+            score += 1;
+        } else if mh & last != 0 {
+            score -= 1;
+        }
+        ph = (ph << 1) | 1;
+        mh <<= 1;
+        pv = mh | !(xv | ph);
+        mv = ph & xv;
+
+        // Each remaining character of b can lower the score by at most one
+        if score.saturating_sub(b.len() - j - 1) > max {
+            return None;
+        }
+    }
+
+    (score <= max).then_some(score)
+}
+
+/// Two-row dynamic programming fallback for long strings
+fn levenshtein_rows(a: &[char], b: &[char]) -> Option<usize> {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur[j + 1] = (prev[j + 1] + 1).min(cur[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    Some(prev[b.len()])
+}
+
+/// Damerau-Levenshtein distance (optimal string alignment): like Levenshtein,
+/// but swapping two adjacent characters counts as one edit ("lgoin" -> "login")
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(before[j - 2] + 1);
+            }
+            cur[j] = d;
+        }
+        std::mem::swap(&mut before, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Whether `a` and `b` are at most `max` typos apart, counting transpositions
+/// as one. Myers' distance rules out most pairs first: a transposition costs
+/// two Levenshtein edits, so OSA distance is at least half of it.
+pub fn within_typos(a: &str, b: &str, max: usize) -> bool {
+    match levenshtein_bounded(a, b, max * 2) {
+        Some(d) if d <= max => true,
+        Some(_) => damerau_levenshtein(a, b) <= max,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levenshtein(a: &str, b: &str) -> usize {
+        levenshtein_bounded(a, b, usize::MAX).unwrap()
+    }
+
+    #[test]
+    fn test_myers_matches_dynamic_programming() {
+        let pairs = [
+            ("", "abc"), ("kitten", "sitting"), ("login", "logn"), ("flaw", "lawn"),
+            ("speichern", "speicher"), ("löschen", "loschen"), ("same", "same"),
+        ];
+        for (a, b) in pairs {
+            let a_chars: Vec<char> = a.chars().collect();
+            let b_chars: Vec<char> = b.chars().collect();
+            assert_eq!(Some(levenshtein(a, b)), levenshtein_rows(&a_chars, &b_chars), "{} / {}", a, b);
+        }
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+
+        let long = "a".repeat(80);
+        assert_eq!(levenshtein(&long, &format!("{}b", long)), 1);
+    }
+
+    #[test]
+    fn test_bounded_and_transpositions() {
+        assert_eq!(levenshtein_bounded("kitten", "sitting", 3), Some(3));
+        assert_eq!(levenshtein_bounded("kitten", "sitting", 2), None);
+        assert_eq!(levenshtein_bounded("a", "abcdef", 2), None);
+
+        assert_eq!(levenshtein("lgoin", "login"), 2);
+        assert_eq!(damerau_levenshtein("lgoin", "login"), 1);
+        assert!(within_typos("lgoin", "login", 1));
+        assert!(!within_typos("lgoin", "logout", 1));
+    }
+}
@@ -2,14 +2,27 @@
 //! High-performance database and query execution for UI element navigation
 
 mod types;
+mod columnar;
 mod db;
 mod query;
 mod tokenizer;
 mod cache;
 mod similarity;
+mod snapshot;
+mod store;
+mod bitset;
+mod synonyms;
+mod trigram;
+mod lsh;
+mod distance;
+mod phonetic;
+mod stem;
 
 use wasm_bindgen::prelude::*;
-use types::NodeRecord;
+use wasm_bindgen::JsCast;
+use types::{ElementRole, NodeRecord};
+use columnar::ColumnarBatch;
+use tokenizer::TextOptions;
 use db::UiDatabase;
 use cache::EmbeddingCache;
 use similarity::{cosine_similarity, top_k_similar};
@@ -50,6 +63,31 @@ impl WasmUiDb {
         Ok(())
     }
 
+    /// Ingest records from a packed columnar batch
+    /// Expects a JS object of typed arrays (see the `columnar` module for the layout)
+    #[wasm_bindgen]
+    pub fn ingest_columnar(&mut self, batch_js: JsValue) -> Result<(), JsValue> {
+        let batch = ColumnarBatch {
+            ids: typed_column::<js_sys::Uint32Array>(&batch_js, "ids")?.to_vec(),
+            frame_ids: typed_column::<js_sys::Uint16Array>(&batch_js, "frameIds")?.to_vec(),
+            roles: typed_column::<js_sys::Uint8Array>(&batch_js, "roles")?.to_vec(),
+            state_bits: typed_column::<js_sys::Uint32Array>(&batch_js, "stateBits")?.to_vec(),
+            rects: typed_column::<js_sys::Int32Array>(&batch_js, "rects")?.to_vec(),
+            names: typed_column::<js_sys::Uint32Array>(&batch_js, "names")?.to_vec(),
+            fingerprints: typed_column::<js_sys::Uint32Array>(&batch_js, "fingerprints")?.to_vec(),
+            tag_names: typed_column::<js_sys::Uint32Array>(&batch_js, "tagNames")?.to_vec(),
+            context_offsets: typed_column::<js_sys::Uint32Array>(&batch_js, "contextOffsets")?.to_vec(),
+            context_ids: typed_column::<js_sys::Uint32Array>(&batch_js, "contextIds")?.to_vec(),
+            attr_offsets: typed_column::<js_sys::Uint32Array>(&batch_js, "attrOffsets")?.to_vec(),
+            attr_ids: typed_column::<js_sys::Uint32Array>(&batch_js, "attrIds")?.to_vec(),
+            string_data: typed_column::<js_sys::Uint8Array>(&batch_js, "stringData")?.to_vec(),
+            string_offsets: typed_column::<js_sys::Uint32Array>(&batch_js, "stringOffsets")?.to_vec(),
+        };
+
+        self.db.ingest_columnar(batch)
+            .map_err(|e| JsValue::from_str(&format!("Failed to ingest columnar batch: {}", e)))
+    }
+
     /// Execute a query and return matches
     /// Expects a JSON query string, returns QueryResult as JS object
     #[wasm_bindgen]
@@ -61,6 +99,83 @@ impl WasmUiDb {
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
     }
 
+    // ==================== Synonym Methods ====================
+
+    /// Replace the synonym table with built-in profiles ("de-en-ui", "fr-ui",
+    /// "es-ui", "it-ui", "nl-ui", "pt-ui", "app-specific" or "none").
+    /// Profiles can be combined with `+`, e.g. "de-en-ui+fr-ui"
+    #[wasm_bindgen]
+    pub fn set_synonym_profile(&mut self, profile: &str) -> Result<(), JsValue> {
+        self.db.set_synonym_profile(profile)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Merge built-in profiles into the current synonym table
+    #[wasm_bindgen]
+    pub fn add_synonym_profile(&mut self, profile: &str) -> Result<(), JsValue> {
+        self.db.add_synonym_profile(profile)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Merge custom synonym groups into the current table
+    /// Expects a JSON array of string arrays or weighted groups, e.g.
+    /// `[["invoice", "bill", "rechnung"], {"terms": ["ok", "submit"], "weight": 0.5}]`
+    #[wasm_bindgen]
+    pub fn load_synonyms(&mut self, groups_json: &str) -> Result<(), JsValue> {
+        self.db.load_synonyms(groups_json)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Remove all synonyms
+    #[wasm_bindgen]
+    pub fn clear_synonyms(&mut self) {
+        self.db.clear_synonyms();
+    }
+
+    /// Get the number of terms that have synonyms
+    #[wasm_bindgen]
+    pub fn synonym_count(&self) -> usize {
+        self.db.synonyms().len()
+    }
+
+    // ==================== Text Methods ====================
+
+    /// Set text normalization and re-index the stored records.
+    /// Expects `{ transliterateGerman?: boolean, foldDiacritics?: boolean,
+    /// stemming?: "en" | "de" | "fr" | "es" | "it" | "nl" | "pt" | null,
+    /// contextTrigrams?: boolean, locale?: string }`; missing fields use their
+    /// defaults (folding on, stemming and context trigrams off, root case rules)
+    #[wasm_bindgen]
+    pub fn set_text_options(&mut self, options_js: JsValue) -> Result<(), JsValue> {
+        let options: TextOptions = serde_wasm_bindgen::from_value(options_js)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse text options: {}", e)))?;
+
+        self.db.set_text_options(options);
+        Ok(())
+    }
+
+    // ==================== Vocabulary Methods ====================
+
+    /// Top `k` indexed words starting with `prefix` by document frequency,
+    /// optionally counting only records with the given role.
+    /// Returns an array of `{ term, frequency }`
+    #[wasm_bindgen]
+    pub fn suggest(&self, prefix: &str, k: usize, role_js: JsValue) -> Result<JsValue, JsValue> {
+        let role: Option<ElementRole> = serde_wasm_bindgen::from_value(role_js)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse role: {}", e)))?;
+
+        serde_wasm_bindgen::to_value(&self.db.suggest(prefix, k, role))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize suggestions: {}", e)))
+    }
+
+    /// All indexed words with their document frequencies, most frequent first.
+    /// Returns an array of `{ term, frequency }`
+    #[wasm_bindgen]
+    pub fn vocabulary(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.db.vocabulary())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize vocabulary: {}", e)))
+    }
+
     /// Get the number of records in the database
     #[wasm_bindgen]
     pub fn size(&self) -> usize {
@@ -78,12 +193,34 @@ impl WasmUiDb {
     #[wasm_bindgen]
     pub fn get_record(&self, id: u32) -> Result<JsValue, JsValue> {
         match self.db.get_record(id) {
-            Some(record) => serde_wasm_bindgen::to_value(record)
+            Some(record) => serde_wasm_bindgen::to_value(&record)
                 .map_err(|e| JsValue::from_str(&format!("Serialization failed: {}", e))),
             None => Ok(JsValue::UNDEFINED),
         }
     }
 
+    // ==================== Snapshot Methods ====================
+
+    /// Serialize records, indices and synonyms into a versioned, checksummed
+    /// snapshot (Uint8Array), optionally including the embedding cache
+    #[wasm_bindgen]
+    pub fn to_bytes(&self, include_embeddings: bool) -> Vec<u8> {
+        let cache = include_embeddings.then_some(&self.embedding_cache);
+        snapshot::encode(&self.db, cache)
+    }
+
+    /// Restore a database from a snapshot produced by `to_bytes`
+    #[wasm_bindgen]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmUiDb, JsValue> {
+        let (db, cache) = snapshot::decode(bytes)
+            .map_err(|e| JsValue::from_str(&format!("Failed to load snapshot: {}", e)))?;
+
+        Ok(WasmUiDb {
+            db,
+            embedding_cache: cache.unwrap_or_default(),
+        })
+    }
+
     // ==================== Embedding Cache Methods ====================
 
     /// Cache an embedding for a fingerprint
@@ -142,11 +279,11 @@ impl WasmUiDb {
     ) -> Result<JsValue, JsValue> {
         // Gather embeddings for candidates that have them cached
         let mut candidates_with_embeddings: Vec<(usize, Vec<f32>)> = Vec::new();
-        let records = self.db.records();
+        let columns = self.db.columns();
 
         for &id in &candidate_ids_js {
-            if let Some(record) = records.iter().find(|r| r.id == id) {
-                if let Some(emb) = self.embedding_cache.peek(&record.fingerprint) {
+            if let Some(idx) = columns.index_of(id) {
+                if let Some(emb) = self.embedding_cache.peek(columns.fingerprint(idx)) {
                     candidates_with_embeddings.push((id as usize, emb.to_vec()));
                 }
             }
@@ -175,6 +312,13 @@ impl WasmUiDb {
     }
 }
 
+/// Read a typed array column from a columnar batch object
+fn typed_column<T: JsCast>(batch: &JsValue, key: &str) -> Result<T, JsValue> {
+    js_sys::Reflect::get(batch, &JsValue::from_str(key))?
+        .dyn_into::<T>()
+        .map_err(|_| JsValue::from_str(&format!("Column {} is missing or has the wrong array type", key)))
+}
+
 /// Semantic match result for JS
 #[derive(serde::Serialize)]
 struct SemanticMatch {
@@ -0,0 +1,116 @@
+//! MinHash signatures with banded LSH buckets for near-duplicate name lookup
+
+use std::hash::{Hash, Hasher};
+use rustc_hash::{FxHashMap, FxHasher};
+use crate::bitset::Bitset;
+use crate::tokenizer::{tokenize, TextOptions};
+use crate::trigram::trigrams;
+
+/// Hash functions per signature
+pub const NUM_HASHES: usize = 60;
+
+/// Signature rows per band; `NUM_HASHES / ROWS` bands
+const ROWS: usize = 3;
+
+/// Estimated Jaccard similarity a bucket hit needs to count as a match
+pub const MIN_SIMILARITY: f64 = 0.5;
+
+/// SplitMix64 finalizer, used to derive independent hash functions
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// MinHash signature over the padded character trigrams of the text's words.
+/// `None` for text without words.
+pub fn signature(text: &str, opts: &TextOptions) -> Option<[u32; NUM_HASHES]> {
+    // Rejoin the words so punctuation doesn't split shingles ("sign-in" ~ "sign in")
+    let shingles = trigrams(&tokenize(text, opts).join(" "), true);
+    if shingles.is_empty() {
+        return None;
+    }
+
+    let mut sig = [u32::MAX; NUM_HASHES];
+    for (i, slot) in sig.iter_mut().enumerate() {
+        let seed = mix(i as u64);
+        for &shingle in &shingles {
+            *slot = (*slot).min((mix(shingle ^ seed) >> 32) as u32);
+        }
+    }
+    Some(sig)
+}
+
+/// Estimated Jaccard similarity of two signatures
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let equal = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    equal as f64 / NUM_HASHES as f64
+}
+
+/// Bucket key of one band of a signature
+fn band_key(band: usize, rows: &[u32]) -> u64 {
+    let mut hasher = FxHasher::default();
+    band.hash(&mut hasher);
+    rows.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// MinHash signatures of every record plus their LSH buckets
+#[derive(Debug, Clone, Default)]
+pub struct LshIndex {
+    /// Signature per record, `None` for records without words
+    signatures: Vec<Option<[u32; NUM_HASHES]>>,
+    buckets: FxHashMap<u64, Vec<usize>>,
+}
+
+impl LshIndex {
+    /// Sign and bucket one text per record
+    pub fn build<'t>(texts: impl Iterator<Item = &'t str>, opts: &TextOptions) -> Self {
+        let signatures: Vec<_> = texts.map(|text| signature(text, opts)).collect();
+        let mut buckets: FxHashMap<u64, Vec<usize>> = FxHashMap::default();
+        for (idx, sig) in signatures.iter().enumerate() {
+            let Some(sig) = sig else { continue };
+            for (band, rows) in sig.chunks(ROWS).enumerate() {
+                buckets.entry(band_key(band, rows)).or_default().push(idx);
+            }
+        }
+        LshIndex { signatures, buckets }
+    }
+
+    /// Records sharing at least one band with `sig`
+    pub fn candidates(&self, sig: &[u32; NUM_HASHES]) -> Bitset {
+        let mut result = Bitset::empty(self.signatures.len());
+        for (band, rows) in sig.chunks(ROWS).enumerate() {
+            for &idx in self.buckets.get(&band_key(band, rows)).map_or(&[][..], Vec::as_slice) {
+                result.insert(idx);
+            }
+        }
+        result
+    }
+
+    /// Estimated similarity between a record and a query signature
+    pub fn similarity_to(&self, idx: usize, sig: &[u32; NUM_HASHES]) -> f64 {
+        self.signatures[idx].as_ref().map_or(0.0, |own| similarity(own, sig))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_near_duplicates() {
+        let opts = TextOptions::default();
+        let names = ["Sign in", "Sign-In", "Sign in now", "Cancel", "Settings", ""];
+        let index = LshIndex::build(names.iter().copied(), &opts);
+
+        let query = signature("sign in", &opts).unwrap();
+        let hits: Vec<usize> = index.candidates(&query)
+            .iter()
+            .filter(|&idx| index.similarity_to(idx, &query) >= MIN_SIMILARITY)
+            .collect();
+        assert_eq!(hits, vec![0, 1, 2]);
+        assert!(signature("", &opts).is_none());
+    }
+}
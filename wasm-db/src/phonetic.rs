@@ -0,0 +1,598 @@
+//! Phonetic codes for matching spoken or transcribed names
+//!
+//! Words are encoded with Double Metaphone (English) and Kölner Phonetik
+//! (German). Index keys carry a prefix per algorithm: `m:`/`k:` for single
+//! words and `M:`/`K:` for the whole phrase with spaces removed, so
+//! "Lock out" still meets "Logout".
+
+use rustc_hash::FxHashMap;
+use crate::bitset::Bitset;
+use crate::tokenizer::{tokenize, TextOptions};
+
+/// Maximum code length for single words, as in the reference algorithm
+const WORD_CODE_LEN: usize = 4;
+
+/// Longer codes for whole phrases so multi-word names stay distinct
+const PHRASE_CODE_LEN: usize = 8;
+
+/// Letters of a word for Double Metaphone, with bounds-checked lookups
+struct Word {
+    chars: Vec<char>,
+}
+
+impl Word {
+    fn at(&self, i: isize) -> char {
+        if i < 0 {
+            return '\0';
+        }
+        self.chars.get(i as usize).copied().unwrap_or('\0')
+    }
+
+    /// Whether one of `options` starts at position `start`
+    fn has(&self, start: isize, options: &[&str]) -> bool {
+        if start < 0 {
+            return false;
+        }
+        let start = start as usize;
+        options.iter().any(|opt| {
+            let end = start + opt.len();
+            end <= self.chars.len() && self.chars[start..end].iter().copied().eq(opt.chars())
+        })
+    }
+
+    fn is_vowel(&self, i: isize) -> bool {
+        matches!(self.at(i), 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+    }
+}
+
+/// Double Metaphone primary and alternate codes of a word, each at most
+/// `max_len` characters. Letters outside A-Z are ignored.
+pub fn double_metaphone(word: &str, max_len: usize) -> (String, String) {
+    let w = Word {
+        chars: word.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_uppercase()).collect(),
+    };
+    let len = w.chars.len() as isize;
+    let last = len - 1;
+    let slavo_germanic = w.has(0, &["W", "K"])
+        || w.chars.iter().any(|&c| c == 'W' || c == 'K')
+        || (0..len).any(|i| w.has(i, &["CZ", "WITZ"]));
+
+    let mut primary = String::new();
+    let mut alternate = String::new();
+    let mut add = |p: &str, a: &str| {
+        primary.push_str(p);
+        alternate.push_str(a);
+    };
+
+    let mut cur: isize = 0;
+    if w.has(0, &["GN", "KN", "PN", "WR", "PS"]) {
+        cur = 1;
+    }
+    if w.at(0) == 'X' {
+        add("S", "S");
+        cur = 1;
+    }
+
+    while cur < len {
+        let c = w.at(cur);
+        let next = w.at(cur + 1);
+        cur += match c {
+            'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                if cur == 0 {
+                    add("A", "A");
+                }
+                1
+            }
+            'B' => {
+                add("P", "P");
+                if next == 'B' { 2 } else { 1 }
+            }
+            'C' => {
+                if cur > 1
+                    && !w.is_vowel(cur - 2)
+                    && w.has(cur - 1, &["ACH"])
+                    && w.at(cur + 2) != 'I'
+                    && (w.at(cur + 2) != 'E' || w.has(cur - 2, &["BACHER", "MACHER"]))
+                {
+                    add("K", "K");
+                    2
+                } else if cur == 0 && w.has(cur, &["CAESAR"]) {
+                    add("S", "S");
+                    2
+                } else if w.has(cur, &["CHIA"]) {
+                    add("K", "K");
+                    2
+                } else if w.has(cur, &["CH"]) {
+                    if cur > 0 && w.has(cur, &["CHAE"]) {
+                        add("K", "X");
+                    } else if (cur == 0
+                        && (w.has(cur + 1, &["HARAC", "HARIS"]) || w.has(cur + 1, &["HOR", "HYM", "HIA", "HEM"]))
+                        && !w.has(0, &["CHORE"]))
+                        || w.has(0, &["SCH"])
+                        || w.has(cur - 2, &["ORCHES", "ARCHIT", "ORCHID"])
+                        || w.has(cur + 2, &["T", "S"])
+                        || ((w.has(cur - 1, &["A", "O", "U", "E"]) || cur == 0)
+                            && (cur + 2 >= len || w.has(cur + 2, &["L", "R", "N", "M", "B", "H", "F", "V", "W"])))
+                    {
+                        add("K", "K");
+                    } else if cur > 0 {
+                        if w.has(0, &["MC"]) { add("K", "K") } else { add("X", "K") }
+                    } else {
+                        add("X", "X");
+                    }
+                    2
+                } else if w.has(cur, &["CZ"]) && !w.has(cur - 2, &["WICZ"]) {
+                    add("S", "X");
+                    2
+                } else if w.has(cur + 1, &["CIA"]) {
+                    add("X", "X");
+                    3
+                } else if w.has(cur, &["CC"]) && !(cur == 1 && w.at(0) == 'M') {
+                    if w.has(cur + 2, &["I", "E", "H"]) && !w.has(cur + 2, &["HU"]) {
+                        if (cur == 1 && w.at(0) == 'A') || w.has(cur - 1, &["UCCEE", "UCCES"]) {
+                            add("KS", "KS");
+                        } else {
+                            add("X", "X");
+                        }
+                        3
+                    } else {
+                        add("K", "K");
+                        2
+                    }
+                } else if w.has(cur, &["CK", "CG", "CQ"]) {
+                    add("K", "K");
+                    2
+                } else if w.has(cur, &["CI", "CE", "CY"]) {
+                    if w.has(cur, &["CIO", "CIE", "CIA"]) { add("S", "X") } else { add("S", "S") }
+                    2
+                } else {
+                    add("K", "K");
+                    if w.has(cur + 1, &["C", "K", "Q"]) && !w.has(cur + 1, &["CE", "CI"]) { 2 } else { 1 }
+                }
+            }
+            'D' => {
+                if w.has(cur, &["DG"]) {
+                    if w.has(cur + 2, &["I", "E", "Y"]) {
+                        add("J", "J");
+                        3
+                    } else {
+                        add("TK", "TK");
+                        2
+                    }
+                } else {
+                    add("T", "T");
+                    if w.has(cur, &["DT", "DD"]) { 2 } else { 1 }
+                }
+            }
+            'F' => {
+                add("F", "F");
+                if next == 'F' { 2 } else { 1 }
+            }
+            'G' => {
+                if next == 'H' {
+                    if cur > 0 && !w.is_vowel(cur - 1) {
+                        add("K", "K");
+                    } else if cur == 0 {
+                        if w.at(cur + 2) == 'I' { add("J", "J") } else { add("K", "K") }
+                    } else if w.has(cur - 2, &["B", "H", "D"])
+                        || w.has(cur - 3, &["B", "H", "D"])
+                        || w.has(cur - 4, &["B", "H"])
+                    {
+                        // Silent, as in "bought" or "hugh"
+                    } else if cur > 2 && w.at(cur - 1) == 'U' && w.has(cur - 3, &["C", "G", "L", "R", "T"]) {
+                        add("F", "F");
+                    } else if cur > 0 && w.at(cur - 1) != 'I' {
+                        add("K", "K");
+                    }
+                    2
+                } else if next == 'N' {
+                    if cur == 1 && w.is_vowel(0) && !slavo_germanic {
+                        add("KN", "N");
+                    } else if !w.has(cur + 2, &["EY"]) && !slavo_germanic {
+                        add("N", "KN");
+                    } else {
+                        add("KN", "KN");
+                    }
+                    2
+                } else if w.has(cur + 1, &["LI"]) && !slavo_germanic {
+                    add("KL", "L");
+                    2
+                } else if (cur == 0
+                    && (next == 'Y' || w.has(1, &["ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER"])))
+                    || ((w.has(cur + 1, &["ER"]) || next == 'Y')
+                        && !w.has(0, &["DANGER", "RANGER", "MANGER"])
+                        && !w.has(cur - 1, &["E", "I"])
+                        && !w.has(cur - 1, &["RGY", "OGY"]))
+                {
+                    add("K", "J");
+                    2
+                } else if w.has(cur + 1, &["E", "I", "Y"]) || w.has(cur - 1, &["AGGI", "OGGI"]) {
+                    if w.has(0, &["SCH"]) || w.has(cur + 1, &["ET"]) {
+                        add("K", "K");
+                    } else if w.has(cur + 1, &["IER"]) && cur + 3 == len {
+                        add("J", "J");
+                    } else {
+                        add("J", "K");
+                    }
+                    2
+                } else {
+                    add("K", "K");
+                    if next == 'G' { 2 } else { 1 }
+                }
+            }
+            'H' if (cur == 0 || w.is_vowel(cur - 1)) && w.is_vowel(cur + 1) => {
+                add("H", "H");
+                2
+            }
+            'J' => {
+                if w.has(cur, &["JOSE"]) {
+                    if cur == 0 && cur + 4 == len { add("H", "H") } else { add("J", "H") }
+                    1
+                } else {
+                    if cur == 0 {
+                        add("J", "A");
+                    } else if w.is_vowel(cur - 1) && !slavo_germanic && (next == 'A' || next == 'O') {
+                        add("J", "H");
+                    } else if cur == last {
+                        add("J", "");
+                    } else if !w.has(cur + 1, &["L", "T", "K", "S", "N", "M", "B", "Z"])
+                        && !w.has(cur - 1, &["S", "K", "L"])
+                    {
+                        add("J", "J");
+                    }
+                    if next == 'J' { 2 } else { 1 }
+                }
+            }
+            'K' => {
+                add("K", "K");
+                if next == 'K' { 2 } else { 1 }
+            }
+            'L' => {
+                if next == 'L' {
+                    if (cur == len - 3 && w.has(cur - 1, &["ILLO", "ILLA", "ALLE"]))
+                        || ((w.has(last - 1, &["AS", "OS"]) || w.has(last, &["A", "O"])) && w.has(cur - 1, &["ALLE"]))
+                    {
+                        add("L", "");
+                    } else {
+                        add("L", "L");
+                    }
+                    2
+                } else {
+                    add("L", "L");
+                    1
+                }
+            }
+            'M' => {
+                add("M", "M");
+                if (w.has(cur - 1, &["UMB"]) && (cur + 1 == last || w.has(cur + 2, &["ER"]))) || next == 'M' {
+                    2
+                } else {
+                    1
+                }
+            }
+            'N' => {
+                add("N", "N");
+                if next == 'N' { 2 } else { 1 }
+            }
+            'P' => {
+                if next == 'H' {
+                    add("F", "F");
+                    2
+                } else {
+                    add("P", "P");
+                    if next == 'P' || next == 'B' { 2 } else { 1 }
+                }
+            }
+            'Q' => {
+                add("K", "K");
+                if next == 'Q' { 2 } else { 1 }
+            }
+            'R' => {
+                if cur == last && !slavo_germanic && w.has(cur - 2, &["IE"]) && !w.has(cur - 4, &["ME", "MA"]) {
+                    add("", "R");
+                } else {
+                    add("R", "R");
+                }
+                if next == 'R' { 2 } else { 1 }
+            }
+            'S' => {
+                if w.has(cur - 1, &["ISL", "YSL"]) {
+                    1
+                } else if cur == 0 && w.has(cur, &["SUGAR"]) {
+                    add("X", "S");
+                    1
+                } else if w.has(cur, &["SH"]) {
+                    if w.has(cur + 1, &["HEIM", "HOEK", "HOLM", "HOLZ"]) { add("S", "S") } else { add("X", "X") }
+                    2
+                } else if w.has(cur, &["SIO", "SIA"]) {
+                    if slavo_germanic { add("S", "S") } else { add("S", "X") }
+                    3
+                } else if (cur == 0 && w.has(cur + 1, &["M", "N", "L", "W"])) || next == 'Z' {
+                    add("S", "X");
+                    if next == 'Z' { 2 } else { 1 }
+                } else if w.has(cur, &["SC"]) {
+                    if w.at(cur + 2) == 'H' {
+                        if w.has(cur + 3, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                            if w.has(cur + 3, &["ER", "EN"]) { add("X", "SK") } else { add("SK", "SK") }
+                        } else if cur == 0 && !w.is_vowel(3) && w.at(3) != 'W' {
+                            add("X", "S");
+                        } else {
+                            add("X", "X");
+                        }
+                    } else if w.has(cur + 2, &["I", "E", "Y"]) {
+                        add("S", "S");
+                    } else {
+                        add("SK", "SK");
+                    }
+                    3
+                } else {
+                    if cur == last && w.has(cur - 2, &["AI", "OI"]) { add("", "S") } else { add("S", "S") }
+                    if next == 'S' || next == 'Z' { 2 } else { 1 }
+                }
+            }
+            'T' => {
+                if w.has(cur, &["TION", "TIA", "TCH"]) {
+                    add("X", "X");
+                    3
+                } else if w.has(cur, &["TH", "TTH"]) {
+                    if w.has(cur + 2, &["OM", "AM"]) || w.has(0, &["SCH"]) { add("T", "T") } else { add("0", "T") }
+                    2
+                } else {
+                    add("T", "T");
+                    if next == 'T' || next == 'D' { 2 } else { 1 }
+                }
+            }
+            'V' => {
+                add("F", "F");
+                if next == 'V' { 2 } else { 1 }
+            }
+            'W' => {
+                if w.has(cur, &["WR"]) {
+                    add("R", "R");
+                    2
+                } else {
+                    if cur == 0 && w.is_vowel(cur + 1) {
+                        add("A", "F");
+                    } else if cur == 0 && next == 'H' {
+                        add("A", "A");
+                    }
+                    if (cur == last && w.is_vowel(cur - 1))
+                        || w.has(cur - 1, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+                        || w.has(0, &["SCH"])
+                    {
+                        add("", "F");
+                        1
+                    } else if w.has(cur, &["WICZ", "WITZ"]) {
+                        add("TS", "FX");
+                        4
+                    } else {
+                        1
+                    }
+                }
+            }
+            'X' => {
+                if !(cur == last && (w.has(cur - 3, &["IAU", "EAU"]) || w.has(cur - 2, &["AU", "OU"]))) {
+                    add("KS", "KS");
+                }
+                if next == 'C' || next == 'X' { 2 } else { 1 }
+            }
+            'Z' => {
+                if next == 'H' {
+                    add("J", "J");
+                    2
+                } else {
+                    if w.has(cur + 1, &["ZO", "ZI", "ZA"]) || (slavo_germanic && cur > 0 && w.at(cur - 1) != 'T') {
+                        add("S", "TS");
+                    } else {
+                        add("S", "S");
+                    }
+                    if next == 'Z' { 2 } else { 1 }
+                }
+            }
+            _ => 1,
+        };
+    }
+
+    primary.truncate(max_len);
+    alternate.truncate(max_len);
+    (primary, alternate)
+}
+
+/// Kölner Phonetik code of a word. Expects lowercase text; umlauts may be
+/// spelled out ("mueller") since vowels all share one code.
+pub fn cologne(word: &str) -> String {
+    let chars: Vec<char> = word.chars()
+        .filter_map(|c| match c {
+            'ä' => Some('a'),
+            'ö' => Some('o'),
+            'ü' => Some('u'),
+            'ß' => Some('s'),
+            c if c.is_ascii_alphabetic() => Some(c.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect();
+
+    let mut digits: Vec<char> = Vec::with_capacity(chars.len() * 2);
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = if i > 0 { chars[i - 1] } else { '\0' };
+        let next = chars.get(i + 1).copied().unwrap_or('\0');
+        let code: &str = match c {
+            'a' | 'e' | 'i' | 'j' | 'o' | 'u' | 'y' => "0",
+            'h' => "",
+            'b' => "1",
+            'p' => if next == 'h' { "3" } else { "1" },
+            'd' | 't' => if matches!(next, 'c' | 's' | 'z') { "8" } else { "2" },
+            'f' | 'v' | 'w' => "3",
+            'g' | 'k' | 'q' => "4",
+            'c' => {
+                let hard = if i == 0 {
+                    matches!(next, 'a' | 'h' | 'k' | 'l' | 'o' | 'q' | 'r' | 'u' | 'x')
+                } else {
+                    matches!(next, 'a' | 'h' | 'k' | 'o' | 'q' | 'u' | 'x') && !matches!(prev, 's' | 'z')
+                };
+                if hard { "4" } else { "8" }
+            }
+            'x' => if matches!(prev, 'c' | 'k' | 'q') { "8" } else { "48" },
+            'l' => "5",
+            'm' | 'n' => "6",
+            'r' => "7",
+            's' | 'z' => "8",
+            _ => "",
+        };
+        digits.extend(code.chars());
+    }
+
+    // Collapse repeats, then drop vowel codes except a leading one
+    digits.dedup();
+    digits.iter()
+        .enumerate()
+        .filter(|&(i, &d)| d != '0' || i == 0)
+        .map(|(_, &d)| d)
+        .collect()
+}
+
+/// Index keys of one word: its Double Metaphone and Kölner codes
+fn word_keys(word: &str, max_len: usize, prefixes: [&str; 2]) -> Vec<String> {
+    let (primary, alternate) = double_metaphone(word, max_len);
+    let cologne = cologne(word);
+
+    let mut keys = Vec::with_capacity(3);
+    for code in [&primary, &alternate] {
+        if !code.is_empty() {
+            keys.push(format!("{}{}", prefixes[0], code));
+        }
+    }
+    if !cologne.is_empty() {
+        keys.push(format!("{}{}", prefixes[1], cologne));
+    }
+    keys.dedup();
+    keys
+}
+
+/// Phonetic keys of a text: per-word keys and keys of the whole phrase
+pub struct PhoneticKeys {
+    pub words: Vec<Vec<String>>,
+    pub phrase: Vec<String>,
+}
+
+impl PhoneticKeys {
+    pub fn of(text: &str, opts: &TextOptions) -> Self {
+        let tokens = tokenize(text, opts);
+        let words: Vec<Vec<String>> = tokens.iter()
+            .map(|t| word_keys(t, WORD_CODE_LEN, ["m:", "k:"]))
+            .filter(|keys| !keys.is_empty())
+            .collect();
+        let phrase = if tokens.len() > 1 {
+            word_keys(&tokens.concat(), PHRASE_CODE_LEN, ["M:", "K:"])
+        } else {
+            Vec::new()
+        };
+        PhoneticKeys { words, phrase }
+    }
+
+    /// Every key, for indexing
+    pub fn all(&self) -> impl Iterator<Item = &String> {
+        self.words.iter().flatten().chain(&self.phrase)
+    }
+
+    /// Whether `other` sounds like this pattern: each pattern word shares a
+    /// code with some word of `other`, or the phrases share a code. Single
+    /// words are also compared against the other side's phrase.
+    pub fn matches(&self, other: &PhoneticKeys) -> bool {
+        if self.words.is_empty() {
+            return false;
+        }
+        let own_phrase = if self.phrase.is_empty() { &self.words[0] } else { &self.phrase };
+        let other_phrase = if other.phrase.is_empty() { other.words.first() } else { Some(&other.phrase) };
+        let same_code = |a: &[String], b: &[String]| {
+            a.iter().any(|x| b.iter().any(|y| x[2..] == y[2..] && x.as_bytes()[0].eq_ignore_ascii_case(&y.as_bytes()[0])))
+        };
+
+        if other_phrase.is_some_and(|p| same_code(own_phrase, p)) {
+            return true;
+        }
+        self.words.iter().all(|keys| other.words.iter().any(|w| same_code(keys, w)))
+    }
+}
+
+/// Build the phonetic index: key -> records whose text has that key
+pub fn build_index<'t>(texts: impl Iterator<Item = &'t str>, opts: &TextOptions) -> FxHashMap<String, Vec<usize>> {
+    let mut index: FxHashMap<String, Vec<usize>> = FxHashMap::default();
+    for (idx, text) in texts.enumerate() {
+        for key in PhoneticKeys::of(text, opts).all() {
+            let list = index.entry(key.clone()).or_default();
+            if list.last() != Some(&idx) {
+                list.push(idx);
+            }
+        }
+    }
+    index
+}
+
+/// Records that sound like `pattern`, looked up in an index from [`build_index`]
+pub fn lookup(index: &FxHashMap<String, Vec<usize>>, len: usize, pattern: &str, opts: &TextOptions) -> Bitset {
+    let keys = PhoneticKeys::of(pattern, opts);
+    let mut result = Bitset::empty(len);
+    if keys.words.is_empty() {
+        return result;
+    }
+
+    let union = |keys: &mut dyn Iterator<Item = String>| {
+        let mut set = Bitset::empty(len);
+        for key in keys {
+            for &idx in index.get(&key).map_or(&[][..], Vec::as_slice) {
+                set.insert(idx);
+            }
+        }
+        set
+    };
+    let upper = |key: &String| format!("{}{}", &key[..1].to_ascii_uppercase(), &key[1..]);
+    let lower = |key: &String| format!("{}{}", &key[..1].to_ascii_lowercase(), &key[1..]);
+
+    // Phrase against phrase, and a single word against a one-word name
+    let own_phrase = if keys.phrase.is_empty() { &keys.words[0] } else { &keys.phrase };
+    result.union_with(&union(&mut own_phrase.iter().flat_map(|k| [upper(k), lower(k)])));
+
+    // Every word somewhere in the name
+    let mut all_words = Bitset::full(len);
+    for word in &keys.words {
+        all_words.intersect_with(&union(&mut word.iter().map(lower)));
+    }
+    result.union_with(&all_words);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        assert_eq!(double_metaphone("sign", 4), ("SN".to_string(), "SKN".to_string()));
+        assert_eq!(double_metaphone("smith", 4), ("SM0".to_string(), "XMT".to_string()));
+        assert_eq!(double_metaphone("knight", 4).0, "NT");
+        assert_eq!(double_metaphone("lockout", 8).0, double_metaphone("logout", 8).0);
+
+        assert_eq!(cologne("müller"), "657");
+        assert_eq!(cologne("mueller"), "657");
+        assert_eq!(cologne("meier"), cologne("mayer"));
+        assert_eq!(cologne("wikipedia"), "3412");
+    }
+
+    #[test]
+    fn test_sounds_like() {
+        let opts = TextOptions::default();
+        let keys = |s: &str| PhoneticKeys::of(s, &opts);
+        assert!(keys("Sign Inn").matches(&keys("Sign in")));
+        assert!(keys("Lock out").matches(&keys("Logout")));
+        assert!(keys("Maier").matches(&keys("Meyer")));
+        assert!(!keys("Sign in").matches(&keys("Settings")));
+
+        let names = ["Sign in", "Logout", "Settings", "Herr Meyer"];
+        let index = build_index(names.iter().copied(), &opts);
+        let ids = |pattern: &str| lookup(&index, names.len(), pattern, &opts).iter().collect::<Vec<_>>();
+        assert_eq!(ids("sign inn"), vec![0]);
+        assert_eq!(ids("lock out"), vec![1]);
+        assert_eq!(ids("maier"), vec![3]);
+    }
+}
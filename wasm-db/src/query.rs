@@ -1,128 +1,578 @@
 //! Query parsing and execution
 
-use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use crate::types::*;
 use crate::types::state_flags::*;
 use crate::db::UiDatabase;
-use crate::tokenizer::{fuzzy_score, match_text};
+use crate::bitset::Bitset;
+use crate::store::Sym;
+use crate::synonyms::SynonymTable;
+use crate::trigram::TrigramIndex;
+use crate::lsh::{signature, MIN_SIMILARITY};
+use crate::phonetic;
+use crate::stem::{StemField, STEM_MATCH_SCORE};
+use crate::tokenizer::{
+    highlight, lowercase, match_score, match_text, match_words, max_typos, normalize, split_identifier, tokenize,
+    TextOptions,
+};
+use crate::distance::{damerau_levenshtein, within_typos};
+
+/// Default score gap under which leading matches count as ambiguous; wide
+/// enough to absorb the upper-viewport boost
+const DEFAULT_AMBIGUITY_GAP: f64 = 0.06;
+
+/// Temperature of softmax scores; a raw score lead of 0.05 makes a match
+/// e times as likely
+const SOFTMAX_TEMPERATURE: f64 = 0.05;
+
+/// Most near-tied matches described in an ambiguity report
+const MAX_AMBIGUOUS: usize = 5;
+
+/// Ranking by score, best first; ambiguity is always judged in this order
+const BY_SCORE: (&str, bool) = ("score", true);
+
+/// Suggestions kept per unknown query word
+const MAX_SPELLING_SUGGESTIONS: usize = 3;
+
+/// Current time in milliseconds from `performance.now()`.
+/// Native builds (e.g. unit tests) have no browser clock and report 0.
+fn now_ms() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|w| w.performance())
+            .map(|p| p.now())
+            .unwrap_or(0.0)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        0.0
+    }
+}
+
+/// Score multiplier for names taken from identifier attributes
+const IDENT_MATCH_WEIGHT: f64 = 0.8;
+
+/// Candidate in a top-k heap. Ordered so the heap's maximum is the entry
+/// ranked last: lower key first, then higher record index.
+struct Ranked {
+    key: f64,
+    idx: usize,
+    score: f64,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.total_cmp(&self.key).then(self.idx.cmp(&other.idx))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// Text a name, context or near clause matches against. Synonym expansions carry the weight
+/// of the edge they came through and the query term they replace.
+struct Pattern {
+    text: String,
+    weight: f64,
+    via: Option<String>,
+    /// Records sharing the stem of every word, from the stem index of the
+    /// clause's field; `None` without stemming
+    stem_hits: Option<Bitset>,
+}
 
 /// Query executor that operates on a database
 pub struct QueryExecutor<'a> {
     db: &'a UiDatabase,
-    synonyms: &'a FxHashMap<String, Vec<String>>,
+    synonyms: &'a SynonymTable,
+    text_options: &'a TextOptions,
+    testid_key: Option<Sym>,
 }
 
 impl<'a> QueryExecutor<'a> {
-    pub fn new(db: &'a UiDatabase, synonyms: &'a FxHashMap<String, Vec<String>>) -> Self {
-        QueryExecutor { db, synonyms }
+    pub fn new(db: &'a UiDatabase, synonyms: &'a SynonymTable) -> Self {
+        let testid_key = db.columns().strings.find("data-testid");
+        QueryExecutor { db, synonyms, text_options: db.text_options(), testid_key }
     }
 
-    /// Execute a query and return results
+    /// Execute a query and return results. A query without matches gets
+    /// spelling suggestions for its name words and, with `autocorrect`, is
+    /// retried once with the best suggestion for each. With `relax`, a query
+    /// that still matches nothing is weakened step by step (see [`Relaxation`])
+    /// until something matches.
     pub fn execute(&self, query: &QueryAST) -> Result<QueryResult, String> {
-        let start = web_sys::window()
-            .and_then(|w| w.performance())
-            .map(|p| p.now())
-            .unwrap_or(0.0);
-
-        let mut filters_applied: Vec<String> = Vec::new();
-        let records = self.db.records();
-        
-        // Start with all indices
-        let mut candidates: FxHashSet<usize> = (0..records.len()).collect();
-        let mut first_filter = true;
+        let start = now_ms();
+        let mut result = self.run(query)?;
+        if result.total > 0 {
+            return Ok(result);
+        }
 
-        // Apply filters
-        for clause in &query.r#where {
-            let filtered = self.apply_filter(clause, &mut filters_applied)?;
-            
-            if first_filter {
-                candidates = filtered;
-                first_filter = false;
-            } else {
-                candidates = candidates.intersection(&filtered).copied().collect();
+        let suggestions = self.spelling_suggestions(query);
+        if query.autocorrect && !suggestions.is_empty() {
+            let retry = self.run(&corrected_query(query, &suggestions, self.text_options))?;
+            if retry.total > 0 {
+                result = retry;
+                result.explain.autocorrected = true;
             }
         }
+        if query.relax && result.total == 0 {
+            let mut relaxed = query.clone();
+            let mut relaxations = Vec::new();
+            for step in Relaxation::ALL {
+                let applied = step.apply(&mut relaxed);
+                if applied.is_empty() {
+                    continue;
+                }
+                relaxations.extend(applied);
+                let retry = self.run(&relaxed)?;
+                if retry.total > 0 {
+                    result = retry;
+                    result.explain.relaxations = relaxations;
+                    break;
+                }
+            }
+        }
+        result.explain.spelling_suggestions = suggestions;
+        result.explain.execution_time_ms = now_ms() - start;
+        Ok(result)
+    }
 
-        // Score candidates
-        let mut scored: Vec<(usize, f64)> = candidates
-            .into_iter()
-            .map(|idx| {
-                let score = self.score_candidate(idx, query);
-                (idx, score)
-            })
+    /// Run a query once as given, without correction or relaxation
+    fn run(&self, query: &QueryAST) -> Result<QueryResult, String> {
+        let start = now_ms();
+
+        let mut filters_applied: Vec<String> = Vec::new();
+        let columns = self.db.columns();
+        
+        // Expand text clauses once; the same patterns drive filtering and scoring
+        let patterns: Vec<Vec<Pattern>> = query.r#where.iter()
+            .map(|clause| self.clause_patterns(clause))
             .collect();
+        let mut synonyms_used = Vec::new();
 
-        // Sort by score (desc) or other criteria
-        if let Some(order_by) = &query.order_by {
-            if let Some(order) = order_by.first() {
-                let field = order.field.as_deref().unwrap_or("score");
-                let desc = order.direction.as_deref() != Some("asc");
-                
-                match field {
-                    "score" => {
-                        if desc {
-                            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-                        } else {
-                            scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-                        }
-                    }
-                    "y" => {
-                        if desc {
-                            scored.sort_by(|a, b| records[b.0].rect.y.cmp(&records[a.0].rect.y));
-                        } else {
-                            scored.sort_by(|a, b| records[a.0].rect.y.cmp(&records[b.0].rect.y));
-                        }
-                    }
-                    "x" => {
-                        if desc {
-                            scored.sort_by(|a, b| records[b.0].rect.x.cmp(&records[a.0].rect.x));
-                        } else {
-                            scored.sort_by(|a, b| records[a.0].rect.x.cmp(&records[b.0].rect.x));
-                        }
-                    }
-                    _ => {
-                        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-                    }
-                }
-            }
-        } else {
-            // Default: sort by score desc
-            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        // Start with all indices; each clause narrows the set in place
+        let mut candidates = Bitset::full(columns.len());
+
+        // Apply filters
+        for (clause, patterns) in query.r#where.iter().zip(&patterns) {
+            self.apply_filter(clause, patterns, &mut candidates, &mut filters_applied, &mut synonyms_used)?;
         }
 
-        let total = scored.len();
+        let calibrated = self.calibrate(&mut candidates, query, &patterns, &mut filters_applied);
 
-        // Apply offset and limit
+        // Total is the candidate count; only offset + limit records are ranked
+        let total = candidates.count();
         let offset = query.offset.unwrap_or(0);
         let limit = query.limit.unwrap_or(10);
-        let paginated: Vec<_> = scored.into_iter().skip(offset).take(limit).collect();
+        let order = sort_order(query);
+        let ranked = self.top_k(&candidates, query, &patterns, offset.saturating_add(limit), order);
+
+        // Ambiguity is about the best-scoring matches, whatever the order and page
+        let leaders = if order == BY_SCORE && ranked.len() >= MAX_AMBIGUOUS.min(total) {
+            ranked[..MAX_AMBIGUOUS.min(ranked.len())].to_vec()
+        } else {
+            self.top_k(&candidates, query, &patterns, MAX_AMBIGUOUS, BY_SCORE)
+        };
+        let gap = query.ambiguity_gap.unwrap_or(DEFAULT_AMBIGUITY_GAP);
+        let ambiguity = self.ambiguity(&self.tied(&candidates, query, &patterns, leaders, gap, total));
+        let paginated: Vec<(usize, f64)> = ranked.into_iter().skip(offset).collect();
+        let paginated: Vec<(usize, f64)> = match &calibrated {
+            Some(scores) => paginated.into_iter().map(|(idx, _)| (idx, scores[idx])).collect(),
+            None => paginated,
+        };
 
         // Convert to MatchResults
         let matches: Vec<MatchResult> = paginated
             .into_iter()
-            .map(|(idx, score)| self.record_to_match(&records[idx], score))
+            .map(|(idx, score)| self.record_to_match(idx, score, self.highlights(idx, query, &patterns)))
             .collect();
 
-        let end = web_sys::window()
-            .and_then(|w| w.performance())
-            .map(|p| p.now())
-            .unwrap_or(0.0);
+        let end = now_ms();
 
         Ok(QueryResult {
             matches,
             total,
+            ambiguity,
             explain: QueryExplain {
                 candidates_considered: total,
                 filters_applied,
                 execution_time_ms: end - start,
+                synonyms_used,
+                spelling_suggestions: Vec::new(),
+                autocorrected: false,
+                relaxations: Vec::new(),
             },
         })
     }
 
-    /// Apply a single filter clause
-    fn apply_filter(&self, clause: &WhereClause, filters_applied: &mut Vec<String>) -> Result<FxHashSet<usize>, String> {
-        let records = self.db.records();
-        let mut result = FxHashSet::default();
+    /// Expanded patterns of a name, context or near clause. Name and context
+    /// patterns also get the records they match by stem.
+    fn clause_patterns(&self, clause: &WhereClause) -> Vec<Pattern> {
+        let (mut patterns, field) = match clause {
+            WhereClause::Name { name } => (self.expand(&name.value, name.case_sensitive), StemField::Name),
+            WhereClause::Context { in_context } => {
+                (self.expand(&in_context.value, in_context.case_sensitive), StemField::Context)
+            }
+            WhereClause::Near { near } => {
+                return near.text.as_deref().map(|t| self.expand(t, false)).unwrap_or_default();
+            }
+            _ => return Vec::new(),
+        };
+        for p in &mut patterns {
+            p.stem_hits = self.db.stem_index().lookup(field, &p.text, self.text_options);
+        }
+        patterns
+    }
+
+    /// Split a text value into its `|` alternatives and add synonym variants,
+    /// strongest first. Every word n-gram of an alternative is looked up, and
+    /// each hit yields a variant with that phrase replaced, so "remove account"
+    /// also matches "delete account". A variant reachable several ways keeps
+    /// its best weight. Case-sensitive patterns keep their case, though
+    /// synonyms are still looked up case-insensitively and normalized.
+    fn expand(&self, value: &str, case_sensitive: bool) -> Vec<Pattern> {
+        let mut patterns: Vec<Pattern> = Vec::new();
+        let mut add = |text: String, weight: f64, via: Option<String>| {
+            match patterns.iter_mut().find(|p| p.text == text) {
+                Some(existing) if existing.weight < weight => {
+                    existing.weight = weight;
+                    existing.via = via;
+                }
+                Some(_) => {}
+                None => patterns.push(Pattern { text, weight, via, stem_hits: None }),
+            }
+        };
+
+        let opts = self.options(case_sensitive);
+        let terms: Vec<String> = value.split('|')
+            .map(|s| lowercase(s.trim(), &opts))
+            .collect();
+        for term in &terms {
+            add(term.clone(), 1.0, None);
+        }
+        for term in &terms {
+            let words: Vec<&str> = term.split_whitespace().collect();
+            for n in (1..=words.len()).rev() {
+                for start in 0..=words.len() - n {
+                    let phrase = words[start..start + n].join(" ");
+                    for syn in self.synonyms.get(&phrase).into_iter().flatten() {
+                        let text = words[..start].iter()
+                            .chain(std::iter::once(&syn.term.as_str()))
+                            .chain(&words[start + n..])
+                            .copied()
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        // Weights are stored as f32; round so explain output stays readable
+                        let weight = (f64::from(syn.weight) * 1000.0).round() / 1000.0;
+                        add(text, weight, Some(phrase.clone()));
+                    }
+                }
+            }
+        }
+
+        patterns.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+        patterns
+    }
+
+    /// Every candidate scoring within `gap` of the best, best first. Starts
+    /// from the score-ranked `leaders` and widens the ranking while its tail
+    /// is still within the gap.
+    fn tied(
+        &self,
+        candidates: &Bitset,
+        query: &QueryAST,
+        patterns: &[Vec<Pattern>],
+        mut leaders: Vec<(usize, f64)>,
+        gap: f64,
+        total: usize,
+    ) -> Vec<(usize, f64)> {
+        let Some(&(_, best)) = leaders.first() else {
+            return leaders;
+        };
+        while leaders.len() < total && leaders.last().is_some_and(|&(_, score)| best - score < gap) {
+            let k = leaders.len().saturating_mul(2).min(total);
+            leaders = self.top_k(candidates, query, patterns, k, BY_SCORE);
+        }
+        leaders.retain(|&(_, score)| best - score < gap);
+        leaders
+    }
+
+    /// Flag the matches of `tied` (best score first, all within the gap) as
+    /// ambiguous when there are several, and work out what tells the leading
+    /// ones apart from all the others
+    fn ambiguity(&self, tied: &[(usize, f64)]) -> Option<Ambiguity> {
+        if tied.len() < 2 {
+            return None;
+        }
+        let columns = self.db.columns();
+        let mut tied_set = Bitset::empty(columns.len());
+        for &(idx, _) in tied {
+            tied_set.insert(idx);
+        }
+
+        // Hints must isolate a match from every tied record, not only the
+        // reported ones
+        let mut tied = tied[..MAX_AMBIGUOUS.min(tied.len())].to_vec();
+        let score_gap = tied[0].1 - tied[tied.len() - 1].1;
+        let headings: Vec<Option<usize>> = tied.iter().map(|&(idx, _)| self.heading_above(idx)).collect();
+
+        // Position counts top to bottom, then left to right
+        let mut by_position: Vec<usize> = tied.iter().map(|&(idx, _)| idx).collect();
+        by_position.sort_by_key(|&idx| (columns.rects[idx].y, columns.rects[idx].x));
+
+        let candidates = tied.drain(..).zip(&headings).map(|((idx, _), &heading)| {
+            let others = || tied_set.iter().filter(move |&other| other != idx);
+            let context: Vec<String> = columns.context(idx)
+                .filter(|ctx| others().all(|other| !columns.context(other).any(|c| c == *ctx)))
+                .map(str::to_string)
+                .collect();
+            let testid = self.testid_key
+                .and_then(|key| columns.attr_by_sym(idx, key))
+                .map(str::to_string);
+
+            // Proposed clauses, strongest first; keep those that single this match out
+            let mut proposals: Vec<WhereClause> = Vec::new();
+            if let Some(testid) = &testid {
+                proposals.push(WhereClause::Attr {
+                    attr: AttrFilter {
+                        name: "data-testid".to_string(),
+                        value: testid.clone(),
+                        match_type: None,
+                        case_sensitive: false,
+                    },
+                });
+            }
+            proposals.extend(context.iter().map(|ctx| WhereClause::Context {
+                in_context: TextFilter { match_type: MatchType::Contains, value: ctx.clone(), case_sensitive: false },
+            }));
+            if let Some(h) = heading {
+                let (cx, cy) = rect_center(&columns.rects[idx]);
+                let (hx, hy) = rect_center(&columns.rects[h]);
+                proposals.push(WhereClause::Near {
+                    near: NearFilter {
+                        target_id: Some(columns.ids[h]),
+                        text: None,
+                        radius: ((cx - hx).powi(2) + (cy - hy).powi(2)).sqrt().ceil() + 1.0,
+                    },
+                });
+            }
+            let suggested_where = proposals.into_iter()
+                .filter(|clause| self.isolates(clause, &tied_set, idx))
+                .collect();
+
+            Disambiguation {
+                id: columns.ids[idx],
+                context,
+                heading: heading.map(|h| columns.name(h).to_string()),
+                position: by_position.iter().position(|&i| i == idx).map_or(0, |p| p + 1),
+                rect: columns.rects[idx],
+                testid,
+                suggested_where,
+            }
+        }).collect();
+
+        Some(Ambiguity { score_gap, candidates })
+    }
+
+    /// Closest heading that ends above the record, preferring headings of its
+    /// own column (horizontal overlap) over closer ones beside it
+    fn heading_above(&self, idx: usize) -> Option<usize> {
+        let columns = self.db.columns();
+        let rect = &columns.rects[idx];
+        self.db.role_index().get(&ElementRole::Heading)?
+            .iter()
+            .copied()
+            .filter(|&h| h != idx && columns.rects[h].y + columns.rects[h].height <= rect.y)
+            .max_by_key(|&h| {
+                let heading = &columns.rects[h];
+                let overlaps = heading.x < rect.x + rect.width && rect.x < heading.x + heading.width;
+                (overlaps, heading.y)
+            })
+    }
+
+    /// Whether adding `clause` keeps `idx` and drops the other records of `tied`
+    fn isolates(&self, clause: &WhereClause, tied: &Bitset, idx: usize) -> bool {
+        let patterns = self.clause_patterns(clause);
+        let mut remaining = tied.clone();
+        let ok = self.apply_filter(clause, &patterns, &mut remaining, &mut Vec::new(), &mut Vec::new()).is_ok();
+        ok && remaining.count() == 1 && remaining.contains(idx)
+    }
+
+    /// Nearest page words for each name word of `query` that no record
+    /// contains, within the typo budget for the word's length (at least one)
+    fn spelling_suggestions(&self, query: &QueryAST) -> Vec<SpellingSuggestion> {
+        let tokens = self.db.token_index();
+        let mut suggestions: Vec<SpellingSuggestion> = Vec::new();
+
+        for clause in &query.r#where {
+            let WhereClause::Name { name } = clause else { continue };
+            for token in tokenize(&name.value, self.text_options) {
+                if tokens.contains_key(&token) || suggestions.iter().any(|s| s.token == token) {
+                    continue;
+                }
+
+                let budget = max_typos(token.chars().count()).max(1);
+                let mut near: Vec<(usize, usize, &String)> = tokens.iter()
+                    .filter(|(term, _)| within_typos(&token, term, budget))
+                    .map(|(term, indices)| (damerau_levenshtein(&token, term), indices.len(), term))
+                    .collect();
+                if near.is_empty() {
+                    continue;
+                }
+                near.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then_with(|| a.2.cmp(b.2)));
+                suggestions.push(SpellingSuggestion {
+                    token,
+                    suggestions: near.into_iter().take(MAX_SPELLING_SUGGESTIONS).map(|(_, _, term)| term.clone()).collect(),
+                });
+            }
+        }
+        suggestions
+    }
+
+    /// Texts a name clause matches against, with their weights: the name,
+    /// then the identifier words at a discount. Name-less records (e.g. icon
+    /// buttons) only have the latter.
+    fn name_texts(&self, idx: usize) -> impl Iterator<Item = (&'a str, f64)> {
+        let name = self.db.columns().name(idx);
+        let named = !name.trim().is_empty();
+        named.then_some((name, 1.0))
+            .into_iter()
+            .chain(std::iter::once((self.db.ident_text(idx), IDENT_MATCH_WEIGHT)))
+    }
+
+    /// Narrow `candidates` to records the trigram index can't rule out
+    /// before the exact per-record check
+    fn shortlist(&self, index: &TrigramIndex, candidates: &mut Bitset, patterns: &[Pattern], match_type: &str) {
+        // Fuzzy matches only need to share some trigrams; stem matches come
+        // from the stem index instead
+        let require_all = match match_type {
+            "fuzzy" | "lsh" => false,
+            // Sound-alike spellings need not share any trigrams
+            "phonetic" => return,
+            // Wildcards aren't part of the text; skip the index
+            "glob" => return,
+            "word" => return self.shortlist_words(index, candidates, patterns),
+            _ => true,
+        };
+        if let Some(mut shortlist) = index.shortlist(patterns.iter().map(|p| p.text.as_str()), require_all, self.text_options) {
+            if stem_fallback(match_type) {
+                for hits in patterns.iter().filter_map(|p| p.stem_hits.as_ref()) {
+                    shortlist.union_with(hits);
+                }
+            }
+            candidates.intersect_with(&shortlist);
+        }
+    }
+
+    /// Shortlist for word matches. Text words may be joined by punctuation
+    /// rather than spaces, so each pattern word needs its trigrams on its own.
+    fn shortlist_words(&self, index: &TrigramIndex, candidates: &mut Bitset, patterns: &[Pattern]) {
+        let len = self.db.columns().len();
+        let mut shortlist = Bitset::empty(len);
+        for p in patterns {
+            let mut matching = Bitset::full(len);
+            // Split the way word matches do, single-character words included
+            for word in match_words(&p.text, self.text_options) {
+                // Words too short for trigrams don't narrow the search
+                if let Some(hits) = index.shortlist(std::iter::once(word.as_str()), true, self.text_options) {
+                    matching.intersect_with(&hits);
+                }
+            }
+            shortlist.union_with(&matching);
+        }
+        candidates.intersect_with(&shortlist);
+    }
+
+    /// Keep candidates that match one of `patterns` according to
+    /// `is_match(record, pattern index)`, crediting each to the strongest
+    /// pattern it matches, and record the synonyms that matched.
+    fn retain_matching(
+        &self,
+        candidates: &mut Bitset,
+        patterns: &[Pattern],
+        is_match: impl Fn(usize, usize) -> bool,
+        synonyms_used: &mut Vec<SynonymUse>,
+    ) {
+        let mut used = vec![false; patterns.len()];
+        candidates.retain(|idx| {
+            match (0..patterns.len()).position(|i| is_match(idx, i)) {
+                Some(i) => {
+                    used[i] = true;
+                    true
+                }
+                None => false,
+            }
+        });
+
+        for (pattern, _) in patterns.iter().zip(used).filter(|(_, used)| *used) {
+            if let Some(term) = &pattern.via {
+                synonyms_used.push(SynonymUse {
+                    term: term.clone(),
+                    synonym: pattern.text.clone(),
+                    weight: pattern.weight,
+                });
+            }
+        }
+    }
+
+    fn text_matches(&self, text: &str, pattern: &Pattern, match_type: &str, opts: &TextOptions) -> bool {
+        match_text(text, std::slice::from_ref(&pattern.text), match_type, opts)
+    }
+
+    /// Whether record `idx` matches `pattern` by stem under `match_type`
+    fn stem_matches(&self, idx: usize, pattern: &Pattern, match_type: &str) -> bool {
+        stem_fallback(match_type) && pattern.stem_hits.as_ref().is_some_and(|hits| hits.contains(idx))
+    }
+
+    /// The database's text options with a filter's case sensitivity
+    fn options(&self, case_sensitive: bool) -> TextOptions {
+        TextOptions { case_sensitive, ..*self.text_options }
+    }
+
+    /// Best pattern score against `text` of record `idx`; synonym variants
+    /// are discounted by their edge weight, and stem matches score at least
+    /// [`STEM_MATCH_SCORE`]
+    fn best_pattern_score(&self, idx: usize, patterns: &[Pattern], text: &str, filter: &TextFilter) -> f64 {
+        let opts = self.options(filter.case_sensitive);
+        let match_type = filter.match_type.as_str();
+        let mut best: f64 = 0.0;
+        for p in patterns {
+            // Sorted by weight, so later patterns can't beat this
+            if p.weight <= best {
+                break;
+            }
+            let mut score = match_score(&p.text, text, match_type, &opts);
+            if score < STEM_MATCH_SCORE && self.stem_matches(idx, p, match_type) {
+                score = STEM_MATCH_SCORE;
+            }
+            best = best.max(score * p.weight);
+        }
+        best
+    }
+
+    /// Apply a single filter clause, removing non-matching records from `candidates`.
+    /// Scanning filters only visit records that are still candidates.
+    fn apply_filter(
+        &self,
+        clause: &WhereClause,
+        patterns: &[Pattern],
+        candidates: &mut Bitset,
+        filters_applied: &mut Vec<String>,
+        synonyms_used: &mut Vec<SynonymUse>,
+    ) -> Result<(), String> {
+        let columns = self.db.columns();
 
         match clause {
             WhereClause::Role { role } => {
@@ -134,60 +584,42 @@ impl<'a> QueryExecutor<'a> {
                 let role_names: Vec<String> = roles.iter().map(|r| format!("{:?}", r).to_lowercase()).collect();
                 filters_applied.push(format!("role={}", role_names.join("|")));
                 
+                let mut matching = Bitset::empty(columns.len());
                 for r in roles {
                     if let Some(indices) = self.db.role_index().get(&r) {
-                        result.extend(indices.iter().copied());
+                        for &idx in indices {
+                            matching.insert(idx);
+                        }
                     }
                 }
+                candidates.intersect_with(&matching);
             }
 
             WhereClause::State { state } => {
                 let mut state_names = Vec::new();
-                
-                for (idx, record) in records.iter().enumerate() {
-                    let mut matches = true;
-                    
-                    if let Some(visible) = state.visible {
-                        let is_visible = (record.state_bits & VISIBLE) != 0;
-                        if is_visible != visible {
-                            matches = false;
-                        }
-                        state_names.push(format!("visible={}", visible));
-                    }
-                    if let Some(enabled) = state.enabled {
-                        let is_enabled = (record.state_bits & ENABLED) != 0;
-                        if is_enabled != enabled {
-                            matches = false;
-                        }
-                        state_names.push(format!("enabled={}", enabled));
-                    }
-                    if let Some(checked) = state.checked {
-                        let is_checked = (record.state_bits & CHECKED) != 0;
-                        if is_checked != checked {
-                            matches = false;
-                        }
-                    }
-                    if let Some(expanded) = state.expanded {
-                        let is_expanded = (record.state_bits & EXPANDED) != 0;
-                        if is_expanded != expanded {
-                            matches = false;
-                        }
-                    }
-                    if let Some(focused) = state.focused {
-                        let is_focused = (record.state_bits & FOCUSED) != 0;
-                        if is_focused != focused {
-                            matches = false;
-                        }
-                    }
-                    if let Some(selected) = state.selected {
-                        let is_selected = (record.state_bits & SELECTED) != 0;
-                        if is_selected != selected {
-                            matches = false;
-                        }
-                    }
-                    
-                    if matches {
-                        result.insert(idx);
+                if let Some(visible) = state.visible {
+                    state_names.push(format!("visible={}", visible));
+                }
+                if let Some(enabled) = state.enabled {
+                    state_names.push(format!("enabled={}", enabled));
+                }
+
+                // Combine the per-flag bitsets word by word
+                let flags = [
+                    (state.visible, VISIBLE),
+                    (state.enabled, ENABLED),
+                    (state.checked, CHECKED),
+                    (state.expanded, EXPANDED),
+                    (state.focused, FOCUSED),
+                    (state.selected, SELECTED),
+                ];
+                let state_index = self.db.state_index();
+                for (wanted, flag) in flags {
+                    let column = &state_index[flag.trailing_zeros() as usize];
+                    match wanted {
+                        Some(true) => candidates.intersect_with(column),
+                        Some(false) => candidates.subtract(column),
+                        None => {}
                     }
                 }
                 
@@ -196,70 +628,75 @@ impl<'a> QueryExecutor<'a> {
             }
 
             WhereClause::Name { name } => {
-                let match_type_str = match name.match_type {
-                    MatchType::Exact => "exact",
-                    MatchType::Contains => "contains",
-                    MatchType::Fuzzy => "fuzzy",
-                    MatchType::Regex => "regex",
-                };
+                let match_type_str = name.match_type.as_str();
+                let opts = &self.options(name.case_sensitive);
                 filters_applied.push(format!("name({}:{})", match_type_str, &name.value));
                 
-                // Split by pipe for alternatives
-                let mut patterns: Vec<String> = name.value.split('|')
-                    .map(|s| s.trim().to_lowercase())
-                    .collect();
-                
-                // Expand with synonyms
-                for pattern in patterns.clone() {
-                    if let Some(syns) = self.synonyms.get(&pattern) {
-                        patterns.extend(syns.clone());
-                    }
-                }
-                
-                for (idx, record) in records.iter().enumerate() {
-                    if match_text(&record.name, &patterns, match_type_str) {
-                        result.insert(idx);
+                if name.match_type == MatchType::Lsh {
+                    // Bucket hits are the shortlist; estimated similarity verifies them
+                    let lsh = self.db.name_lsh();
+                    let signatures: Vec<_> = patterns.iter()
+                        .map(|p| signature(&p.text, self.text_options))
+                        .collect();
+                    let mut shortlist = Bitset::empty(columns.len());
+                    for sig in signatures.iter().flatten() {
+                        shortlist.union_with(&lsh.candidates(sig));
                     }
+                    candidates.intersect_with(&shortlist);
+                    self.retain_matching(candidates, patterns, |idx, i| {
+                        signatures[i].as_ref().is_some_and(|sig| lsh.similarity_to(idx, sig) >= MIN_SIMILARITY)
+                    }, synonyms_used);
+                } else if name.match_type == MatchType::Phonetic {
+                    // The code index answers each pattern exactly
+                    let hits: Vec<Bitset> = patterns.iter()
+                        .map(|p| phonetic::lookup(self.db.phonetic_index(), columns.len(), &p.text, self.text_options))
+                        .collect();
+                    self.retain_matching(candidates, patterns, |idx, i| hits[i].contains(idx), synonyms_used);
+                } else {
+                    let mut by_ident = candidates.clone();
+                    self.shortlist(self.db.name_trigrams(), candidates, patterns, match_type_str);
+                    self.shortlist(self.db.ident_trigrams(), &mut by_ident, patterns, match_type_str);
+                    candidates.union_with(&by_ident);
+                    self.retain_matching(candidates, patterns, |idx, i| {
+                        self.stem_matches(idx, &patterns[i], match_type_str)
+                            || self.name_texts(idx).any(|(text, _)| self.text_matches(text, &patterns[i], match_type_str, opts))
+                    }, synonyms_used);
                 }
             }
 
             WhereClause::Context { in_context } => {
-                let match_type_str = match in_context.match_type {
-                    MatchType::Exact => "exact",
-                    MatchType::Contains => "contains",
-                    MatchType::Fuzzy => "fuzzy",
-                    MatchType::Regex => "regex",
-                };
+                let match_type_str = in_context.match_type.as_str();
+                let opts = &self.options(in_context.case_sensitive);
                 filters_applied.push(format!("context({}:{})", match_type_str, &in_context.value));
                 
-                let patterns: Vec<String> = in_context.value.split('|')
-                    .map(|s| s.trim().to_lowercase())
-                    .collect();
-                
-                for (idx, record) in records.iter().enumerate() {
-                    let context_text = record.context.join(" ");
-                    if match_text(&context_text, &patterns, match_type_str) {
-                        result.insert(idx);
-                    }
+                if let Some(index) = self.db.context_trigrams() {
+                    self.shortlist(index, candidates, patterns, match_type_str);
                 }
+                self.retain_matching(candidates, patterns, |idx, i| {
+                    self.stem_matches(idx, &patterns[i], match_type_str)
+                        || self.text_matches(columns.context_text(idx), &patterns[i], match_type_str, opts)
+                }, synonyms_used);
             }
 
             WhereClause::Attr { attr } => {
-                let match_type_str = attr.match_type.map(|m| match m {
-                    MatchType::Exact => "exact",
-                    MatchType::Contains => "contains",
-                    MatchType::Fuzzy => "fuzzy",
-                    MatchType::Regex => "regex",
-                }).unwrap_or("exact");
+                let match_type_str = attr.match_type.map_or("exact", MatchType::as_str);
+                let opts = &self.options(attr.case_sensitive);
                 
                 filters_applied.push(format!("attr({}={})", &attr.name, &attr.value));
                 
-                for (idx, record) in records.iter().enumerate() {
-                    if let Some(attr_value) = record.attrs.get(&attr.name) {
-                        if match_text(attr_value, &[attr.value.clone()], match_type_str) {
-                            result.insert(idx);
-                        }
+                match columns.strings.find(&attr.name) {
+                    Some(key) => {
+                        let patterns = std::slice::from_ref(&attr.value);
+                        // Identifier values also match by their words, so
+                        // `submitOrderBtn` has the word "order"
+                        candidates.retain(|idx| {
+                            columns.attr_by_sym(idx, key).is_some_and(|value| {
+                                match_text(value, patterns, match_type_str, opts)
+                                    || match_text(&split_identifier(value, opts).join(" "), patterns, match_type_str, opts)
+                            })
+                        });
                     }
+                    None => candidates.clear(),
                 }
             }
 
@@ -267,67 +704,179 @@ impl<'a> QueryExecutor<'a> {
                 filters_applied.push(format!("near({:?}, r={})", near.target_id.or(near.text.as_ref().map(|_| 0)), near.radius));
                 
                 let target_center = if let Some(target_id) = near.target_id {
-                    records.iter().find(|r| r.id == target_id).map(|r| {
-                        let cx = r.rect.x as f64 + r.rect.width as f64 / 2.0;
-                        let cy = r.rect.y as f64 + r.rect.height as f64 / 2.0;
-                        (cx, cy)
-                    })
-                } else if let Some(text) = &near.text {
-                    let text_lower = text.to_lowercase();
-                    records.iter().find(|r| r.name.to_lowercase().contains(&text_lower)).map(|r| {
-                        let cx = r.rect.x as f64 + r.rect.width as f64 / 2.0;
-                        let cy = r.rect.y as f64 + r.rect.height as f64 / 2.0;
-                        (cx, cy)
-                    })
+                    columns.index_of(target_id).map(|idx| rect_center(&columns.rects[idx]))
+                } else if near.text.is_some() {
+                    // Anchor on the strongest pattern that any name contains
+                    let names: Vec<String> = (0..columns.len())
+                        .map(|idx| normalize(columns.name(idx), self.text_options))
+                        .collect();
+                    patterns.iter()
+                        .find_map(|p| {
+                            let text = normalize(&p.text, self.text_options);
+                            let idx = names.iter().position(|name| name.contains(&text))?;
+                            if let Some(term) = &p.via {
+                                synonyms_used.push(SynonymUse {
+                                    term: term.clone(),
+                                    synonym: p.text.clone(),
+                                    weight: p.weight,
+                                });
+                            }
+                            Some(idx)
+                        })
+                        .map(|idx| rect_center(&columns.rects[idx]))
                 } else {
                     None
                 };
                 
-                if let Some((tx, ty)) = target_center {
-                    for (idx, record) in records.iter().enumerate() {
-                        let cx = record.rect.x as f64 + record.rect.width as f64 / 2.0;
-                        let cy = record.rect.y as f64 + record.rect.height as f64 / 2.0;
+                match target_center {
+                    Some((tx, ty)) => candidates.retain(|idx| {
+                        let (cx, cy) = rect_center(&columns.rects[idx]);
                         let distance = ((cx - tx).powi(2) + (cy - ty).powi(2)).sqrt();
-                        
-                        if distance <= near.radius {
-                            result.insert(idx);
-                        }
-                    }
+                        distance <= near.radius
+                    }),
+                    None => candidates.clear(),
                 }
             }
 
             WhereClause::Nth { nth: _ } => {
                 filters_applied.push("nth".to_string());
-                // Return all, nth is applied post-filter
-                result.extend(0..records.len());
+                // Keep all, nth is applied post-filter
             }
         }
 
-        Ok(result)
+        Ok(())
+    }
+
+    /// Select the best `k` candidates under `(field, descending)` with a
+    /// bounded heap. When ranking by score, the fuzzy text scores are only
+    /// computed for candidates whose upper bound can still displace the
+    /// current k-th entry.
+    fn top_k(
+        &self,
+        candidates: &Bitset,
+        query: &QueryAST,
+        patterns: &[Vec<Pattern>],
+        k: usize,
+        (field, desc): (&str, bool),
+    ) -> Vec<(usize, f64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let rects = &self.db.columns().rects;
+
+        // Larger key = ranked first; ties go to the lower record index
+        // `k` comes from the query's offset + limit and may be huge
+        let mut heap: BinaryHeap<Ranked> =
+            BinaryHeap::with_capacity(k.min(candidates.count()).saturating_add(1));
+        let push = |heap: &mut BinaryHeap<Ranked>, key: f64, idx: usize, score: f64| {
+            heap.push(Ranked { key, idx, score });
+            if heap.len() > k {
+                heap.pop();
+            }
+        };
+
+        match field {
+            "y" | "x" => {
+                for idx in candidates.iter() {
+                    let pos = if field == "y" { rects[idx].y } else { rects[idx].x } as f64;
+                    push(&mut heap, if desc { pos } else { -pos }, idx, 0.0);
+                }
+            }
+            _ => {
+                // Unknown fields fall back to score descending
+                let desc = desc || field != "score";
+                for idx in candidates.iter() {
+                    let (base, max_text) = self.score_bounds(idx, query);
+                    if heap.len() == k {
+                        let best_possible = if desc {
+                            cap(query, base + max_text)
+                        } else {
+                            -cap(query, base)
+                        };
+                        if best_possible <= heap.peek().map_or(f64::MIN, |w| w.key) {
+                            continue;
+                        }
+                    }
+                    let score = cap(query, base + self.text_score(idx, query, patterns));
+                    push(&mut heap, if desc { score } else { -score }, idx, score);
+                }
+            }
+        }
+
+        let positional = matches!(field, "y" | "x");
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|r| {
+                let score = if positional { self.score_candidate(r.idx, query, patterns) } else { r.score };
+                (r.idx, score)
+            })
+            .collect()
     }
 
     /// Score a candidate based on query matching
-    fn score_candidate(&self, idx: usize, query: &QueryAST) -> f64 {
-        let record = &self.db.records()[idx];
+    fn score_candidate(&self, idx: usize, query: &QueryAST, patterns: &[Vec<Pattern>]) -> f64 {
+        let (base, _) = self.score_bounds(idx, query);
+        cap(query, base + self.text_score(idx, query, patterns))
+    }
+
+    /// Reported score of every candidate, when `min_score` or softmax scoring
+    /// needs them up front; drops candidates below `min_score`
+    fn calibrate(
+        &self,
+        candidates: &mut Bitset,
+        query: &QueryAST,
+        patterns: &[Vec<Pattern>],
+        filters_applied: &mut Vec<String>,
+    ) -> Option<Vec<f64>> {
+        let softmax = query.score_mode == ScoreMode::Softmax;
+        if query.min_score.is_none() && !softmax {
+            return None;
+        }
+
+        let mut scores = vec![0.0; self.db.columns().len()];
+        for idx in candidates.iter() {
+            scores[idx] = self.score_candidate(idx, query, patterns);
+        }
+        if softmax {
+            // Shift by the best score so exp() can't overflow
+            let best = candidates.iter().map(|idx| scores[idx]).fold(f64::MIN, f64::max);
+            let mut total = 0.0;
+            for idx in candidates.iter() {
+                scores[idx] = ((scores[idx] - best) / SOFTMAX_TEMPERATURE).exp();
+                total += scores[idx];
+            }
+            for idx in candidates.iter() {
+                scores[idx] /= total;
+            }
+        }
+        if let Some(min) = query.min_score {
+            filters_applied.push(format!("min_score({})", min));
+            candidates.retain(|idx| scores[idx] >= min);
+        }
+        Some(scores)
+    }
+
+    /// Cheap part of the score, plus the most the fuzzy text scores could add
+    fn score_bounds(&self, idx: usize, query: &QueryAST) -> (f64, f64) {
+        let columns = self.db.columns();
         let mut score = 0.5; // Base score
+        let mut max_text = 0.0;
 
         for clause in &query.r#where {
             match clause {
-                WhereClause::Name { name } => {
-                    let name_score = fuzzy_score(&name.value, &record.name);
-                    score += name_score * 0.3;
+                WhereClause::Name { .. } => {
+                    max_text += 0.3;
                 }
-                WhereClause::Context { in_context } => {
-                    let context_text = record.context.join(" ");
-                    let context_score = fuzzy_score(&in_context.value, &context_text);
-                    score += context_score * 0.2;
+                WhereClause::Context { .. } => {
+                    max_text += 0.2;
                 }
                 WhereClause::Role { role } => {
-                    let roles = match role {
-                        RoleValue::Single(r) => vec![*r],
-                        RoleValue::Multiple(rs) => rs.clone(),
+                    let matches = match role {
+                        RoleValue::Single(r) => *r == columns.roles[idx],
+                        RoleValue::Multiple(rs) => rs.contains(&columns.roles[idx]),
                     };
-                    if roles.contains(&record.role) {
+                    if matches {
                         score += 0.1;
                     }
                 }
@@ -339,22 +888,48 @@ impl<'a> QueryExecutor<'a> {
         }
 
         // Boost for data-testid
-        if record.attrs.contains_key("data-testid") {
+        if self.testid_key.is_some_and(|key| columns.attr_by_sym(idx, key).is_some()) {
             score += 0.1;
         }
 
         // Boost for upper viewport position
-        if record.rect.y < 300 {
+        if columns.rects[idx].y < 300 {
             score += 0.05;
         }
 
-        score.min(1.0)
+        (score, max_text)
+    }
+
+    /// Fuzzy name and context contribution to the score
+    fn text_score(&self, idx: usize, query: &QueryAST, patterns: &[Vec<Pattern>]) -> f64 {
+        let columns = self.db.columns();
+        let mut score = 0.0;
+
+        for (clause, patterns) in query.r#where.iter().zip(patterns) {
+            match clause {
+                WhereClause::Name { name: filter } => {
+                    let best = self.name_texts(idx)
+                        .map(|(name, weight)| self.best_pattern_score(idx, patterns, name, filter) * weight)
+                        .fold(0.0, f64::max);
+                    score += best * 0.3;
+                }
+                WhereClause::Context { in_context } => {
+                    score += self.best_pattern_score(idx, patterns, columns.context_text(idx), in_context) * 0.2;
+                }
+                _ => {}
+            }
+        }
+
+        score
     }
 
-    /// Convert NodeRecord to MatchResult
-    fn record_to_match(&self, record: &NodeRecord, score: f64) -> MatchResult {
-        let is_visible = (record.state_bits & VISIBLE) != 0;
-        let is_enabled = (record.state_bits & ENABLED) != 0;
+    /// Convert a stored record to MatchResult
+    fn record_to_match(&self, idx: usize, score: f64, highlights: Vec<Highlight>) -> MatchResult {
+        let columns = self.db.columns();
+        let role = columns.roles[idx];
+        let state_bits = columns.state_bits[idx];
+        let is_visible = (state_bits & VISIBLE) != 0;
+        let is_enabled = (state_bits & ENABLED) != 0;
         let actionable = is_visible && is_enabled;
 
         let clickable_roles = [
@@ -378,27 +953,209 @@ impl<'a> QueryExecutor<'a> {
         ];
 
         MatchResult {
-            id: record.id,
+            id: columns.ids[idx],
             score: (score * 100.0).round() / 100.0,
-            role: record.role,
-            name: record.name.clone(),
+            role,
+            name: columns.name(idx).to_string(),
             states: MatchStates {
                 visible: is_visible,
                 enabled: is_enabled,
-                checked: if (record.state_bits & CHECKED) != 0 { Some(true) } else { None },
-                expanded: if (record.state_bits & EXPANDED) != 0 { Some(true) } else { None },
-                focused: if (record.state_bits & FOCUSED) != 0 { Some(true) } else { None },
-                selected: if (record.state_bits & SELECTED) != 0 { Some(true) } else { None },
+                checked: if (state_bits & CHECKED) != 0 { Some(true) } else { None },
+                expanded: if (state_bits & EXPANDED) != 0 { Some(true) } else { None },
+                focused: if (state_bits & FOCUSED) != 0 { Some(true) } else { None },
+                selected: if (state_bits & SELECTED) != 0 { Some(true) } else { None },
             },
-            context: record.context.clone(),
+            context: columns.context(idx).map(str::to_string).collect(),
             actionability: Actionability {
-                click: actionable && clickable_roles.contains(&record.role),
-                r#type: actionable && typeable_roles.contains(&record.role),
-                check: actionable && checkable_roles.contains(&record.role),
-                select: actionable && selectable_roles.contains(&record.role),
+                click: actionable && clickable_roles.contains(&role),
+                r#type: actionable && typeable_roles.contains(&role),
+                check: actionable && checkable_roles.contains(&role),
+                select: actionable && selectable_roles.contains(&role),
                 scroll: is_visible,
             },
-            rect: record.rect.clone(),
+            rect: columns.rects[idx],
+            highlights,
+        }
+    }
+
+    /// Ranges of the record's name, context and attributes that the query's
+    /// text filters matched, each credited to the strongest matching pattern
+    fn highlights(&self, idx: usize, query: &QueryAST, patterns: &[Vec<Pattern>]) -> Vec<Highlight> {
+        let columns = self.db.columns();
+        let mut highlights = Vec::new();
+        let mut push = |field: &str, index: Option<usize>, attr: Option<&str>, ranges: Vec<(usize, usize)>, term: &str| {
+            highlights.extend(ranges.into_iter().map(|(start, end)| Highlight {
+                field: field.to_string(),
+                index,
+                attr: attr.map(str::to_string),
+                start,
+                end,
+                term: term.to_string(),
+            }));
+        };
+
+        for (clause, patterns) in query.r#where.iter().zip(patterns) {
+            match clause {
+                WhereClause::Name { name } => {
+                    let text = columns.name(idx);
+                    let match_type = name.match_type.as_str();
+                    let opts = &self.options(name.case_sensitive);
+                    if let Some(p) = self.matched_pattern(idx, text, patterns, match_type, opts) {
+                        push("name", None, None, highlight(text, &p.text, match_type, opts), &p.text);
+                    }
+                }
+                WhereClause::Context { in_context } => {
+                    let match_type = in_context.match_type.as_str();
+                    let opts = &self.options(in_context.case_sensitive);
+                    if let Some(p) = self.matched_pattern(idx, columns.context_text(idx), patterns, match_type, opts) {
+                        for (i, entry) in columns.context(idx).enumerate() {
+                            push("context", Some(i), None, highlight(entry, &p.text, match_type, opts), &p.text);
+                        }
+                    }
+                }
+                WhereClause::Attr { attr } => {
+                    let match_type = attr.match_type.map_or("exact", MatchType::as_str);
+                    let opts = &self.options(attr.case_sensitive);
+                    let value = columns.strings.find(&attr.name).and_then(|key| columns.attr_by_sym(idx, key));
+                    if let Some(value) = value {
+                        push("attr", None, Some(&attr.name), highlight(value, &attr.value, match_type, opts), &attr.value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        highlights
+    }
+
+    /// Strongest pattern that matches `text` of record `idx`; index-backed
+    /// match types (LSH, phonetic) fall back to the strongest pattern
+    fn matched_pattern<'p>(
+        &self,
+        idx: usize,
+        text: &str,
+        patterns: &'p [Pattern],
+        match_type: &str,
+        opts: &TextOptions,
+    ) -> Option<&'p Pattern> {
+        patterns.iter()
+            .find(|p| self.text_matches(text, p, match_type, opts) || self.stem_matches(idx, p, match_type))
+            .or_else(|| patterns.first())
+    }
+}
+
+/// Field and direction of the query's first `order_by` entry; score,
+/// descending by default
+fn sort_order(query: &QueryAST) -> (&str, bool) {
+    let order = query.order_by.as_ref().and_then(|o| o.first());
+    let field = order.and_then(|o| o.field.as_deref()).unwrap_or("score");
+    let desc = order.and_then(|o| o.direction.as_deref()) != Some("asc");
+    (field, desc)
+}
+
+/// Score as reported under the query's score mode: capped at 1.0 unless
+/// raw or softmax scores were asked for
+fn cap(query: &QueryAST, score: f64) -> f64 {
+    match query.score_mode {
+        ScoreMode::Clamped => score.min(1.0),
+        ScoreMode::Raw | ScoreMode::Softmax => score,
+    }
+}
+
+/// Match types that also accept records sharing the pattern's word stems
+fn stem_fallback(match_type: &str) -> bool {
+    matches!(match_type, "contains" | "fuzzy" | "lsh")
+}
+
+/// Center point of a rectangle
+fn rect_center(rect: &Rect) -> (f64, f64) {
+    (rect.x as f64 + rect.width as f64 / 2.0, rect.y as f64 + rect.height as f64 / 2.0)
+}
+
+/// Ways to weaken a query without matches, tried in order; each step keeps
+/// the ones before it
+#[derive(Debug, Clone, Copy)]
+enum Relaxation {
+    ExactToContains,
+    ContainsToFuzzy,
+    DropContext,
+    DropState,
+}
+
+impl Relaxation {
+    const ALL: [Relaxation; 4] = [
+        Relaxation::ExactToContains,
+        Relaxation::ContainsToFuzzy,
+        Relaxation::DropContext,
+        Relaxation::DropState,
+    ];
+
+    /// Weaken `query` in place and describe each change; empty when the
+    /// step doesn't apply
+    fn apply(self, query: &mut QueryAST) -> Vec<String> {
+        let mut applied = Vec::new();
+        match self {
+            Relaxation::ExactToContains | Relaxation::ContainsToFuzzy => {
+                let (from, to) = match self {
+                    Relaxation::ExactToContains => (MatchType::Exact, MatchType::Contains),
+                    _ => (MatchType::Contains, MatchType::Fuzzy),
+                };
+                for clause in &mut query.r#where {
+                    let (kind, filter) = match clause {
+                        WhereClause::Name { name } => ("name", name),
+                        WhereClause::Context { in_context } => ("context", in_context),
+                        _ => continue,
+                    };
+                    if filter.match_type == from {
+                        filter.match_type = to;
+                        applied.push(format!("{}({}->{}:{})", kind, from.as_str(), to.as_str(), filter.value));
+                    }
+                }
+            }
+            Relaxation::DropContext => query.r#where.retain(|clause| match clause {
+                WhereClause::Context { in_context } => {
+                    applied.push(format!("drop context({})", in_context.value));
+                    false
+                }
+                _ => true,
+            }),
+            Relaxation::DropState => query.r#where.retain(|clause| match clause {
+                WhereClause::State { .. } => {
+                    applied.push("drop state".to_string());
+                    false
+                }
+                _ => true,
+            }),
+        }
+        applied
+    }
+}
+
+/// Copy of `query` with each suggested-for word in its name clauses replaced
+/// by its best suggestion, and autocorrection turned off
+fn corrected_query(query: &QueryAST, suggestions: &[SpellingSuggestion], opts: &TextOptions) -> QueryAST {
+    let correct = |value: &str| -> String {
+        value.split('|')
+            .map(|alternative| {
+                alternative.split_whitespace()
+                    .map(|word| {
+                        let word_lower = normalize(word, opts);
+                        suggestions.iter()
+                            .find(|s| s.token == word_lower)
+                            .map_or(word, |s| s.suggestions[0].as_str())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("|")
+    };
+
+    let mut corrected = query.clone();
+    corrected.autocorrect = false;
+    for clause in &mut corrected.r#where {
+        if let WhereClause::Name { name } = clause {
+            name.value = correct(&name.value);
         }
     }
+    corrected
 }
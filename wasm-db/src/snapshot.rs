@@ -0,0 +1,349 @@
+//! Binary snapshot format for persisting and transferring a database
+//!
+//! Layout: `magic (4) | version (u16) | flags (u16) | body | crc32 (u32)`.
+//! Integers in the body are LEB128 varints, floats are little-endian.
+
+use crate::cache::EmbeddingCache;
+use crate::db::UiDatabase;
+
+/// Magic bytes identifying a UI-Ground snapshot
+pub const MAGIC: &[u8; 4] = b"UIGS";
+
+/// Current snapshot format version
+pub const VERSION: u16 = 7;
+
+/// Header flag: an embedding cache section follows the database section
+pub const FLAG_EMBEDDINGS: u16 = 1 << 0;
+
+const HEADER_LEN: usize = 8;
+const CHECKSUM_LEN: usize = 4;
+
+/// Append-only writer for snapshot bodies
+pub struct SnapshotWriter {
+    buf: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    pub fn new() -> Self {
+        SnapshotWriter { buf: Vec::new() }
+    }
+
+    pub fn put_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    /// Write an unsigned LEB128 varint
+    pub fn put_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    /// Write a signed integer as a zigzag varint
+    pub fn put_signed(&mut self, value: i64) {
+        self.put_varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    pub fn put_len(&mut self, len: usize) {
+        self.put_varint(len as u64);
+    }
+
+    pub fn put_f32(&mut self, value: f32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_str(&mut self, value: &str) {
+        self.put_len(value.len());
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+
+    /// Write an ascending index list as deltas
+    pub fn put_sorted_indices(&mut self, indices: &[usize]) {
+        self.put_len(indices.len());
+        let mut prev = 0;
+        for &idx in indices {
+            self.put_len(idx - prev);
+            prev = idx;
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Default for SnapshotWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cursor over a snapshot body
+pub struct SnapshotReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        SnapshotReader { buf, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() - self.pos < n {
+            return Err("Unexpected end of snapshot".to_string());
+        }
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err("Varint overflow in snapshot".to_string());
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    pub fn signed(&mut self) -> Result<i64, String> {
+        let raw = self.varint()?;
+        Ok(((raw >> 1) as i64) ^ -((raw & 1) as i64))
+    }
+
+    /// Read a length, rejecting values that cannot fit in the remaining bytes
+    pub fn length(&mut self) -> Result<usize, String> {
+        let len = self.varint()? as usize;
+        if len > self.buf.len() - self.pos {
+            return Err(format!("Invalid length {} in snapshot", len));
+        }
+        Ok(len)
+    }
+
+    /// Read an unsigned value that must fit in `u32`
+    pub fn u32(&mut self) -> Result<u32, String> {
+        u32::try_from(self.varint()?).map_err(|_| "Value out of range in snapshot".to_string())
+    }
+
+    /// Read an unsigned value that must fit in `u16`
+    pub fn u16(&mut self) -> Result<u16, String> {
+        u16::try_from(self.varint()?).map_err(|_| "Value out of range in snapshot".to_string())
+    }
+
+    /// Read a signed value that must fit in `i32`
+    pub fn i32(&mut self) -> Result<i32, String> {
+        i32::try_from(self.signed()?).map_err(|_| "Value out of range in snapshot".to_string())
+    }
+
+    pub fn f32(&mut self) -> Result<f32, String> {
+        let bytes = self.take(4)?;
+        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn string(&mut self) -> Result<String, String> {
+        let len = self.length()?;
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes)
+            .map(|s| s.to_string())
+            .map_err(|_| "Invalid UTF-8 in snapshot".to_string())
+    }
+
+    /// Read a delta-encoded index list, checking every index against `bound`
+    pub fn sorted_indices(&mut self, bound: usize) -> Result<Vec<usize>, String> {
+        let count = self.length()?;
+        let mut indices = Vec::with_capacity(count);
+        let mut prev = 0usize;
+        for _ in 0..count {
+            let idx = prev
+                .checked_add(self.varint()? as usize)
+                .filter(|&i| i < bound)
+                .ok_or_else(|| "Index out of range in snapshot".to_string())?;
+            indices.push(idx);
+            prev = idx;
+        }
+        Ok(indices)
+    }
+
+    /// Read a record index, checking it against `bound`
+    pub fn index(&mut self, bound: usize) -> Result<usize, String> {
+        let idx = self.varint()? as usize;
+        if idx >= bound {
+            return Err("Index out of range in snapshot".to_string());
+        }
+        Ok(idx)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+}
+
+/// Serialize a database, and optionally its embedding cache, into a snapshot
+pub fn encode(db: &UiDatabase, cache: Option<&EmbeddingCache>) -> Vec<u8> {
+    let flags = if cache.is_some() { FLAG_EMBEDDINGS } else { 0 };
+
+    let mut w = SnapshotWriter::new();
+    w.buf.extend_from_slice(MAGIC);
+    w.buf.extend_from_slice(&VERSION.to_le_bytes());
+    w.buf.extend_from_slice(&flags.to_le_bytes());
+
+    db.write_snapshot(&mut w);
+    if let Some(cache) = cache {
+        cache.write_snapshot(&mut w);
+    }
+
+    let mut bytes = w.into_bytes();
+    let checksum = crc32fast::hash(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+/// Validate and deserialize a snapshot produced by [`encode`]
+pub fn decode(bytes: &[u8]) -> Result<(UiDatabase, Option<EmbeddingCache>), String> {
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN || &bytes[..4] != MAGIC {
+        return Err("Not a UI-Ground snapshot".to_string());
+    }
+
+    let (content, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    if crc32fast::hash(content) != expected {
+        return Err("Snapshot checksum mismatch".to_string());
+    }
+
+    let version = u16::from_le_bytes([content[4], content[5]]);
+    if version != VERSION {
+        return Err(format!("Unsupported snapshot version {} (expected {})", version, VERSION));
+    }
+    let flags = u16::from_le_bytes([content[6], content[7]]);
+
+    let mut r = SnapshotReader::new(&content[HEADER_LEN..]);
+    let db = UiDatabase::read_snapshot(&mut r)?;
+    let cache = if flags & FLAG_EMBEDDINGS != 0 {
+        Some(EmbeddingCache::read_snapshot(&mut r)?)
+    } else {
+        None
+    };
+
+    if !r.is_empty() {
+        return Err("Trailing data in snapshot".to_string());
+    }
+
+    Ok((db, cache))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+    use std::collections::HashMap;
+
+    fn sample_records() -> Vec<NodeRecord> {
+        let mut attrs = HashMap::new();
+        attrs.insert("data-testid".to_string(), "login-btn".to_string());
+        vec![
+            NodeRecord {
+                id: 7,
+                frame_id: 0,
+                role: ElementRole::Button,
+                name: "Anmelden".to_string(),
+                state_bits: state_flags::VISIBLE | state_flags::ENABLED,
+                attrs,
+                context: vec!["Login dialog".to_string()],
+                rect: Rect { x: -4, y: 120, width: 80, height: 32 },
+                fingerprint: "fp-7".to_string(),
+                tag_name: "button".to_string(),
+            },
+            NodeRecord {
+                id: 9,
+                frame_id: 2,
+                role: ElementRole::Textbox,
+                name: "E-Mail".to_string(),
+                state_bits: state_flags::VISIBLE,
+                attrs: HashMap::new(),
+                context: vec![],
+                rect: Rect { x: 10, y: 60, width: 200, height: 24 },
+                fingerprint: "fp-9".to_string(),
+                tag_name: "input".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut db = UiDatabase::new();
+        db.ingest(sample_records());
+        let mut cache = EmbeddingCache::new(4);
+        cache.put("fp-7".to_string(), vec![0.6, 0.8]);
+
+        let bytes = encode(&db, Some(&cache));
+        let (restored, restored_cache) = decode(&bytes).unwrap();
+
+        assert_eq!(restored.size(), 2);
+        let record = restored.get_record(7).unwrap();
+        assert_eq!(record.name, "Anmelden");
+        assert_eq!(record.rect.x, -4);
+        assert_eq!(record.attrs.get("data-testid").map(String::as_str), Some("login-btn"));
+        assert_eq!(restored.token_index().get("anmelden"), Some(&vec![0]));
+        assert_eq!(restored.testid_index().get("login-btn"), Some(&0));
+        assert_eq!(restored.role_index().get(&ElementRole::Textbox), Some(&vec![1]));
+
+        let restored_cache = restored_cache.unwrap();
+        assert_eq!(restored_cache.capacity(), 4);
+        assert_eq!(restored_cache.peek("fp-7"), Some([0.6, 0.8].as_slice()));
+
+        // Snapshots are deterministic
+        assert_eq!(encode(&restored, Some(&restored_cache)), bytes);
+    }
+
+    #[test]
+    fn test_reads_v7_fixture() {
+        // sample_records() with the "fp-7" embedding cached; regenerate only
+        // together with a VERSION bump
+        let fixture = include_bytes!("../tests/fixtures/snapshot-v7.bin");
+        assert_eq!(&fixture[4..6], &7u16.to_le_bytes());
+
+        let (db, cache) = decode(fixture).unwrap();
+        assert_eq!(db.size(), 2);
+        assert_eq!(db.get_record(9).unwrap().name, "E-Mail");
+        assert_eq!(db.testid_index().get("login-btn"), Some(&0));
+        assert_eq!(cache.unwrap().peek("fp-7"), Some([0.6, 0.8].as_slice()));
+
+        let result = db.query(r#"{"where": [{"name": {"match": "exact", "value": "login"}}]}"#).unwrap();
+        assert_eq!(result.matches[0].id, 7);
+
+        // The writer still produces the pinned bytes
+        let mut expected = UiDatabase::new();
+        expected.ingest(sample_records());
+        let mut cache = EmbeddingCache::new(4);
+        cache.put("fp-7".to_string(), vec![0.6, 0.8]);
+        assert_eq!(encode(&expected, Some(&cache)), fixture.to_vec());
+    }
+
+    #[test]
+    fn test_rejects_corruption() {
+        let mut db = UiDatabase::new();
+        db.ingest(sample_records());
+        let mut bytes = encode(&db, None);
+
+        assert!(decode(&bytes).unwrap().1.is_none());
+
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0xff;
+        assert_eq!(decode(&bytes).err().unwrap(), "Snapshot checksum mismatch");
+        assert!(decode(b"nope").is_err());
+    }
+}
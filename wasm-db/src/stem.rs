@@ -0,0 +1,98 @@
+//! Stem index backing the stem fallback of contains and fuzzy matches
+
+use rustc_hash::FxHashMap;
+use crate::bitset::Bitset;
+use crate::tokenizer::{stems, TextOptions};
+
+/// Score for matches that only agree after stemming, below a plain
+/// substring hit
+pub const STEM_MATCH_SCORE: f64 = 0.75;
+
+/// Record text a stem lookup searches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StemField {
+    /// The matchable name (identifier words for name-less records)
+    Name,
+    /// All context entries
+    Context,
+}
+
+/// Stem -> record indices, kept apart from the surface-word token index
+#[derive(Debug, Clone, Default)]
+pub struct StemIndex {
+    name: FxHashMap<String, Vec<usize>>,
+    context: FxHashMap<String, Vec<usize>>,
+    len: usize,
+}
+
+impl StemIndex {
+    /// Index the stems of each record's name and context text; both
+    /// iterators yield one entry per record. Empty when stemming is off.
+    pub fn build<'t>(
+        names: impl Iterator<Item = &'t str>,
+        contexts: impl Iterator<Item = &'t str>,
+        opts: &TextOptions,
+    ) -> Self {
+        let mut index = StemIndex::default();
+        if opts.stemming.is_none() {
+            return index;
+        }
+        for (idx, (name, context)) in names.zip(contexts).enumerate() {
+            for (postings, text) in [(&mut index.name, name), (&mut index.context, context)] {
+                for stem in stems(text, opts) {
+                    let list = postings.entry(stem).or_default();
+                    if list.last() != Some(&idx) {
+                        list.push(idx);
+                    }
+                }
+            }
+            index.len = idx + 1;
+        }
+        index
+    }
+
+    /// Records whose `field` has, for every word of `pattern`, a word with
+    /// the same stem. `None` when stemming is off or the pattern has no words.
+    pub fn lookup(&self, field: StemField, pattern: &str, opts: &TextOptions) -> Option<Bitset> {
+        let pattern_stems = stems(pattern, opts);
+        if pattern_stems.is_empty() {
+            return None;
+        }
+        let postings = match field {
+            StemField::Name => &self.name,
+            StemField::Context => &self.context,
+        };
+
+        let mut result = Bitset::full(self.len);
+        for stem in &pattern_stems {
+            let mut posting = Bitset::empty(self.len);
+            for &idx in postings.get(stem).map_or(&[][..], Vec::as_slice) {
+                posting.insert(idx);
+            }
+            result.intersect_with(&posting);
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::StemLanguage;
+
+    #[test]
+    fn test_lookup() {
+        let en = TextOptions { stemming: Some(StemLanguage::En), ..TextOptions::default() };
+        let names = ["Deleting items", "Delete", "Settings"];
+        let contexts = ["", "Saved settings", ""];
+        let index = StemIndex::build(names.into_iter(), contexts.into_iter(), &en);
+
+        let ids = |field, pattern| index.lookup(field, pattern, &en).unwrap().iter().collect::<Vec<_>>();
+        assert_eq!(ids(StemField::Name, "deleted"), vec![0, 1]);
+        assert_eq!(ids(StemField::Name, "delete item"), vec![0]);
+        assert_eq!(ids(StemField::Context, "setting"), vec![1]);
+
+        let plain = StemIndex::build(names.into_iter(), contexts.into_iter(), &TextOptions::default());
+        assert!(plain.lookup(StemField::Name, "delete", &TextOptions::default()).is_none());
+    }
+}
@@ -0,0 +1,396 @@
+//! Columnar record storage backed by an interned string pool
+
+use std::hash::{Hash, Hasher};
+use rustc_hash::{FxHashMap, FxHasher};
+use crate::types::{ElementRole, NodeRecord, Rect};
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+
+/// Handle to a string in a [`StringPool`]
+pub type Sym = u32;
+
+/// Deduplicating string pool. All strings share one buffer; lookups go through
+/// a hash of the contents so no string is stored twice.
+#[derive(Default)]
+pub struct StringPool {
+    data: String,
+    spans: Vec<(u32, u32)>,
+    lookup: FxHashMap<u64, Sym>,
+    /// Strings whose hash collides with an earlier, different string
+    collisions: FxHashMap<Box<str>, Sym>,
+}
+
+impl StringPool {
+    fn hash(value: &str) -> u64 {
+        let mut hasher = FxHasher::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Intern a string, returning the existing handle if already present
+    pub fn intern(&mut self, value: &str) -> Sym {
+        let hash = Self::hash(value);
+        match self.lookup.get(&hash) {
+            Some(&sym) if self.get(sym) == value => sym,
+            Some(_) => {
+                if let Some(&sym) = self.collisions.get(value) {
+                    return sym;
+                }
+                let sym = self.push(value);
+                self.collisions.insert(value.into(), sym);
+                sym
+            }
+            None => {
+                let sym = self.push(value);
+                self.lookup.insert(hash, sym);
+                sym
+            }
+        }
+    }
+
+    fn push(&mut self, value: &str) -> Sym {
+        let start = self.data.len() as u32;
+        self.data.push_str(value);
+        self.spans.push((start, self.data.len() as u32));
+        (self.spans.len() - 1) as Sym
+    }
+
+    /// Find the handle of a string without interning it
+    pub fn find(&self, value: &str) -> Option<Sym> {
+        match self.lookup.get(&Self::hash(value)) {
+            Some(&sym) if self.get(sym) == value => Some(sym),
+            Some(_) => self.collisions.get(value).copied(),
+            None => None,
+        }
+    }
+
+    /// Resolve a handle to its string
+    pub fn get(&self, sym: Sym) -> &str {
+        let (start, end) = self.spans[sym as usize];
+        &self.data[start as usize..end as usize]
+    }
+
+    /// Number of distinct strings
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+}
+
+/// Records stored column-wise. Row `i` of every column belongs to the record
+/// at index `i`; variable-length fields are ranges into shared flat columns.
+#[derive(Default)]
+pub struct ColumnStore {
+    pub ids: Vec<u32>,
+    pub frame_ids: Vec<u16>,
+    pub roles: Vec<ElementRole>,
+    pub state_bits: Vec<u32>,
+    pub rects: Vec<Rect>,
+    pub names: Vec<Sym>,
+    /// Context entries joined with spaces, for whole-context matching
+    pub context_texts: Vec<Sym>,
+    pub fingerprints: Vec<Sym>,
+    pub tag_names: Vec<Sym>,
+    /// `n + 1` offsets into `context`
+    pub context_offsets: Vec<u32>,
+    pub context: Vec<Sym>,
+    /// `n + 1` offsets into `attrs`
+    pub attr_offsets: Vec<u32>,
+    pub attrs: Vec<(Sym, Sym)>,
+    pub strings: StringPool,
+    id_lookup: FxHashMap<u32, usize>,
+}
+
+impl ColumnStore {
+    pub fn new() -> Self {
+        ColumnStore {
+            context_offsets: vec![0],
+            attr_offsets: vec![0],
+            ..Default::default()
+        }
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn clear(&mut self) {
+        *self = ColumnStore::new();
+    }
+
+    /// Append one record, interning its strings
+    pub fn push_record(&mut self, record: &NodeRecord) {
+        let attrs: Vec<(Sym, Sym)> = record.attrs
+            .iter()
+            .map(|(k, v)| (self.strings.intern(k), self.strings.intern(v)))
+            .collect();
+
+        let context: Vec<Sym> = record.context.iter().map(|c| self.strings.intern(c)).collect();
+        let name = self.strings.intern(&record.name);
+        let fingerprint = self.strings.intern(&record.fingerprint);
+        let tag_name = self.strings.intern(&record.tag_name);
+
+        self.push_row(
+            record.id,
+            record.frame_id,
+            record.role,
+            record.state_bits,
+            record.rect,
+            [name, fingerprint, tag_name],
+            &context,
+            &attrs,
+        );
+    }
+
+    /// Append one record from already-interned parts.
+    /// `strings` holds the name, fingerprint and tag name.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_row(
+        &mut self,
+        id: u32,
+        frame_id: u16,
+        role: ElementRole,
+        state_bits: u32,
+        rect: Rect,
+        strings: [Sym; 3],
+        context: &[Sym],
+        attrs: &[(Sym, Sym)],
+    ) {
+        let idx = self.ids.len();
+        self.id_lookup.entry(id).or_insert(idx);
+
+        let context_text = match context {
+            [] => self.strings.intern(""),
+            [single] => *single,
+            _ => {
+                let joined = context
+                    .iter()
+                    .map(|&c| self.strings.get(c))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                self.strings.intern(&joined)
+            }
+        };
+
+        self.ids.push(id);
+        self.frame_ids.push(frame_id);
+        self.roles.push(role);
+        self.state_bits.push(state_bits);
+        self.rects.push(rect);
+        self.names.push(strings[0]);
+        self.fingerprints.push(strings[1]);
+        self.tag_names.push(strings[2]);
+        self.context_texts.push(context_text);
+        self.context.extend_from_slice(context);
+        self.context_offsets.push(self.context.len() as u32);
+        self.attrs.extend_from_slice(attrs);
+        self.attr_offsets.push(self.attrs.len() as u32);
+    }
+
+    /// Record index for a record id
+    pub fn index_of(&self, id: u32) -> Option<usize> {
+        self.id_lookup.get(&id).copied()
+    }
+
+    pub fn name(&self, idx: usize) -> &str {
+        self.strings.get(self.names[idx])
+    }
+
+    pub fn fingerprint(&self, idx: usize) -> &str {
+        self.strings.get(self.fingerprints[idx])
+    }
+
+    pub fn tag_name(&self, idx: usize) -> &str {
+        self.strings.get(self.tag_names[idx])
+    }
+
+    /// Context entries joined with spaces
+    pub fn context_text(&self, idx: usize) -> &str {
+        self.strings.get(self.context_texts[idx])
+    }
+
+    pub fn context_syms(&self, idx: usize) -> &[Sym] {
+        &self.context[self.context_offsets[idx] as usize..self.context_offsets[idx + 1] as usize]
+    }
+
+    pub fn context(&self, idx: usize) -> impl Iterator<Item = &str> {
+        self.context_syms(idx).iter().map(|&s| self.strings.get(s))
+    }
+
+    pub fn attr_syms(&self, idx: usize) -> &[(Sym, Sym)] {
+        &self.attrs[self.attr_offsets[idx] as usize..self.attr_offsets[idx + 1] as usize]
+    }
+
+    pub fn attrs(&self, idx: usize) -> impl Iterator<Item = (&str, &str)> {
+        self.attr_syms(idx)
+            .iter()
+            .map(|&(k, v)| (self.strings.get(k), self.strings.get(v)))
+    }
+
+    /// Look up an attribute value by key
+    #[cfg(test)]
+    pub fn attr(&self, idx: usize, key: &str) -> Option<&str> {
+        let key = self.strings.find(key)?;
+        self.attr_by_sym(idx, key)
+    }
+
+    /// Look up an attribute value by an interned key
+    pub fn attr_by_sym(&self, idx: usize, key: Sym) -> Option<&str> {
+        self.attr_syms(idx)
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, v)| self.strings.get(v))
+    }
+
+    /// Materialize a record as an owned `NodeRecord`
+    pub fn record(&self, idx: usize) -> NodeRecord {
+        NodeRecord {
+            id: self.ids[idx],
+            frame_id: self.frame_ids[idx],
+            role: self.roles[idx],
+            name: self.name(idx).to_string(),
+            state_bits: self.state_bits[idx],
+            attrs: self.attrs(idx).map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            context: self.context(idx).map(str::to_string).collect(),
+            rect: self.rects[idx],
+            fingerprint: self.fingerprint(idx).to_string(),
+            tag_name: self.tag_name(idx).to_string(),
+        }
+    }
+
+    /// Serialize the string pool followed by every row
+    pub fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        w.put_len(self.strings.len());
+        for sym in 0..self.strings.len() {
+            w.put_str(self.strings.get(sym as Sym));
+        }
+
+        w.put_len(self.len());
+        for idx in 0..self.len() {
+            w.put_varint(self.ids[idx] as u64);
+            w.put_varint(self.frame_ids[idx] as u64);
+            w.put_u8(self.roles[idx].code());
+            w.put_varint(self.state_bits[idx] as u64);
+
+            let rect = self.rects[idx];
+            w.put_signed(rect.x as i64);
+            w.put_signed(rect.y as i64);
+            w.put_signed(rect.width as i64);
+            w.put_signed(rect.height as i64);
+
+            w.put_varint(self.names[idx] as u64);
+            w.put_varint(self.fingerprints[idx] as u64);
+            w.put_varint(self.tag_names[idx] as u64);
+
+            let context = self.context_syms(idx);
+            w.put_len(context.len());
+            for &sym in context {
+                w.put_varint(sym as u64);
+            }
+
+            let attrs = self.attr_syms(idx);
+            w.put_len(attrs.len());
+            for &(key, value) in attrs {
+                w.put_varint(key as u64);
+                w.put_varint(value as u64);
+            }
+        }
+    }
+
+    /// Rebuild columns written by [`ColumnStore::write_snapshot`]
+    pub fn read_snapshot(r: &mut SnapshotReader) -> Result<Self, String> {
+        let mut store = ColumnStore::new();
+
+        let string_count = r.length()?;
+        for expected in 0..string_count {
+            if store.strings.intern(&r.string()?) as usize != expected {
+                return Err("Duplicate string in snapshot".to_string());
+            }
+        }
+        let sym = |r: &mut SnapshotReader| -> Result<Sym, String> {
+            Ok(r.index(string_count)? as Sym)
+        };
+
+        let row_count = r.length()?;
+        let mut context = Vec::new();
+        let mut attrs = Vec::new();
+        for _ in 0..row_count {
+            let id = r.u32()?;
+            let frame_id = r.u16()?;
+            let role = ElementRole::from_code(r.u8()?)
+                .ok_or_else(|| "Unknown role in snapshot".to_string())?;
+            let state_bits = r.u32()?;
+            let rect = Rect {
+                x: r.i32()?,
+                y: r.i32()?,
+                width: r.i32()?,
+                height: r.i32()?,
+            };
+            let strings = [sym(r)?, sym(r)?, sym(r)?];
+
+            context.clear();
+            for _ in 0..r.length()? {
+                context.push(sym(r)?);
+            }
+            attrs.clear();
+            for _ in 0..r.length()? {
+                attrs.push((sym(r)?, sym(r)?));
+            }
+
+            store.push_row(id, frame_id, role, state_bits, rect, strings, &context, &attrs);
+        }
+
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_string_pool_dedup() {
+        let mut pool = StringPool::default();
+        let a = pool.intern("Settings");
+        let b = pool.intern("Save");
+        assert_eq!(pool.intern("Settings"), a);
+        assert_ne!(a, b);
+        assert_eq!(pool.get(b), "Save");
+        assert_eq!(pool.find("Save"), Some(b));
+        assert_eq!(pool.find("Cancel"), None);
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_column_store_roundtrip() {
+        let mut attrs = HashMap::new();
+        attrs.insert("data-testid".to_string(), "save".to_string());
+        attrs.insert("aria-label".to_string(), "Save changes".to_string());
+        let record = NodeRecord {
+            id: 42,
+            frame_id: 1,
+            role: ElementRole::Button,
+            name: "Save".to_string(),
+            state_bits: 3,
+            attrs,
+            context: vec!["Settings".to_string(), "Profile".to_string()],
+            rect: Rect { x: 1, y: 2, width: 3, height: 4 },
+            fingerprint: "fp".to_string(),
+            tag_name: "button".to_string(),
+        };
+
+        let mut store = ColumnStore::new();
+        store.push_record(&record);
+        store.push_record(&record);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.index_of(42), Some(0));
+        assert_eq!(store.context_text(1), "Settings Profile");
+        assert_eq!(store.attr(1, "data-testid"), Some("save"));
+        assert_eq!(store.attr(1, "id"), None);
+        assert_eq!(store.record(1).attrs, record.attrs);
+        // Both rows share the same interned strings
+        assert_eq!(store.names[0], store.names[1]);
+    }
+}
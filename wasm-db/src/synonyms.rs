@@ -0,0 +1,408 @@
+//! Synonym tables for multilingual name matching
+
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+use crate::tokenizer::{normalize, TextOptions};
+
+/// Weight of groups that don't specify one
+pub const DEFAULT_WEIGHT: f32 = 0.9;
+
+/// Weight for loose equivalents that only sometimes mean the same thing
+const LOOSE: f32 = 0.6;
+
+/// A group of interchangeable terms and the weight of every edge between them
+type Group = (f32, &'static [&'static str]);
+
+/// Common English/German UI vocabulary
+const DE_EN_UI: &[Group] = &[
+    (DEFAULT_WEIGHT, &["login", "sign in", "anmelden", "einloggen", "log in"]),
+    (DEFAULT_WEIGHT, &["logout", "sign out", "abmelden", "log out"]),
+    (DEFAULT_WEIGHT, &["submit", "send", "absenden", "senden", "confirm"]),
+    (LOOSE, &["submit", "ok"]),
+    (DEFAULT_WEIGHT, &["cancel", "abbrechen"]),
+    (LOOSE, &["cancel", "close", "schließen"]),
+    (DEFAULT_WEIGHT, &["save", "speichern"]),
+    (LOOSE, &["save", "apply"]),
+    (DEFAULT_WEIGHT, &["delete", "remove", "löschen", "entfernen"]),
+    (DEFAULT_WEIGHT, &["edit", "bearbeiten", "modify", "ändern"]),
+    (DEFAULT_WEIGHT, &["search", "suchen", "find", "finden"]),
+    (DEFAULT_WEIGHT, &["next", "weiter", "continue", "fortfahren"]),
+    (DEFAULT_WEIGHT, &["back", "zurück", "previous"]),
+    (DEFAULT_WEIGHT, &["home", "startseite"]),
+    (LOOSE, &["home", "main"]),
+    (DEFAULT_WEIGHT, &["settings", "einstellungen", "preferences", "options"]),
+    (DEFAULT_WEIGHT, &["help", "hilfe", "support"]),
+    (DEFAULT_WEIGHT, &["profile", "profil", "account", "konto"]),
+    (DEFAULT_WEIGHT, &["password", "passwort", "kennwort"]),
+    (DEFAULT_WEIGHT, &["email", "e-mail", "mail"]),
+    (DEFAULT_WEIGHT, &["username", "benutzername"]),
+    (LOOSE, &["username", "user"]),
+];
+
+// Locale packs cover the core UI verbs. Each group starts with the English
+// term so packs merge with each other and with the de-en table.
+
+/// French UI verbs
+const FR_UI: &[Group] = &[
+    (DEFAULT_WEIGHT, &["login", "connexion", "se connecter", "s'identifier"]),
+    (DEFAULT_WEIGHT, &["logout", "déconnexion", "se déconnecter"]),
+    (DEFAULT_WEIGHT, &["submit", "envoyer", "soumettre", "valider", "confirmer"]),
+    (DEFAULT_WEIGHT, &["cancel", "annuler"]),
+    (LOOSE, &["cancel", "close", "fermer"]),
+    (DEFAULT_WEIGHT, &["save", "enregistrer", "sauvegarder"]),
+    (DEFAULT_WEIGHT, &["delete", "supprimer", "effacer", "retirer"]),
+    (DEFAULT_WEIGHT, &["search", "rechercher", "recherche", "chercher"]),
+    (DEFAULT_WEIGHT, &["next", "suivant", "continuer"]),
+    (DEFAULT_WEIGHT, &["back", "retour", "précédent"]),
+];
+
+/// Spanish UI verbs
+const ES_UI: &[Group] = &[
+    (DEFAULT_WEIGHT, &["login", "iniciar sesión", "acceder", "entrar", "ingresar"]),
+    (DEFAULT_WEIGHT, &["logout", "cerrar sesión", "salir"]),
+    (DEFAULT_WEIGHT, &["submit", "enviar", "confirmar", "aceptar"]),
+    (DEFAULT_WEIGHT, &["cancel", "cancelar"]),
+    (LOOSE, &["cancel", "close", "cerrar"]),
+    (DEFAULT_WEIGHT, &["save", "guardar"]),
+    (DEFAULT_WEIGHT, &["delete", "eliminar", "borrar", "quitar"]),
+    (DEFAULT_WEIGHT, &["search", "buscar", "búsqueda"]),
+    (DEFAULT_WEIGHT, &["next", "siguiente", "continuar"]),
+    (DEFAULT_WEIGHT, &["back", "atrás", "volver", "anterior"]),
+];
+
+/// Italian UI verbs
+const IT_UI: &[Group] = &[
+    (DEFAULT_WEIGHT, &["login", "accedi", "accesso", "entra"]),
+    (DEFAULT_WEIGHT, &["logout", "esci", "disconnetti"]),
+    (DEFAULT_WEIGHT, &["submit", "invia", "conferma"]),
+    (DEFAULT_WEIGHT, &["cancel", "annulla"]),
+    (LOOSE, &["cancel", "close", "chiudi"]),
+    (DEFAULT_WEIGHT, &["save", "salva"]),
+    (DEFAULT_WEIGHT, &["delete", "elimina", "cancella", "rimuovi"]),
+    (DEFAULT_WEIGHT, &["search", "cerca", "ricerca"]),
+    (DEFAULT_WEIGHT, &["next", "avanti", "successivo", "continua"]),
+    (DEFAULT_WEIGHT, &["back", "indietro", "precedente"]),
+];
+
+/// Dutch UI verbs
+const NL_UI: &[Group] = &[
+    (DEFAULT_WEIGHT, &["login", "inloggen", "aanmelden"]),
+    (DEFAULT_WEIGHT, &["logout", "uitloggen", "afmelden"]),
+    (DEFAULT_WEIGHT, &["submit", "verzenden", "versturen", "bevestigen"]),
+    (DEFAULT_WEIGHT, &["cancel", "annuleren"]),
+    (LOOSE, &["cancel", "close", "sluiten"]),
+    (DEFAULT_WEIGHT, &["save", "opslaan", "bewaren"]),
+    (DEFAULT_WEIGHT, &["delete", "verwijderen", "wissen"]),
+    (DEFAULT_WEIGHT, &["search", "zoeken"]),
+    (DEFAULT_WEIGHT, &["next", "volgende", "verder", "doorgaan"]),
+    (DEFAULT_WEIGHT, &["back", "terug", "vorige"]),
+];
+
+/// Portuguese UI verbs
+const PT_UI: &[Group] = &[
+    (DEFAULT_WEIGHT, &["login", "entrar", "iniciar sessão", "acessar", "fazer login"]),
+    (DEFAULT_WEIGHT, &["logout", "sair", "terminar sessão", "encerrar sessão"]),
+    (DEFAULT_WEIGHT, &["submit", "enviar", "confirmar", "submeter"]),
+    (DEFAULT_WEIGHT, &["cancel", "cancelar"]),
+    (LOOSE, &["cancel", "close", "fechar"]),
+    (DEFAULT_WEIGHT, &["save", "salvar", "guardar"]),
+    (DEFAULT_WEIGHT, &["delete", "excluir", "eliminar", "apagar", "remover"]),
+    (DEFAULT_WEIGHT, &["search", "pesquisar", "buscar", "procurar"]),
+    (DEFAULT_WEIGHT, &["next", "próximo", "seguinte", "continuar", "avançar"]),
+    (DEFAULT_WEIGHT, &["back", "voltar", "anterior"]),
+];
+
+/// Look up the synonym groups of a built-in profile.
+/// `"app-specific"` and `"none"` start empty; app-specific tables are
+/// filled with [`SynonymTable::load_json`].
+fn builtin_profile(name: &str) -> Option<&'static [Group]> {
+    match name {
+        "de-en-ui" => Some(DE_EN_UI),
+        "fr-ui" => Some(FR_UI),
+        "es-ui" => Some(ES_UI),
+        "it-ui" => Some(IT_UI),
+        "nl-ui" => Some(NL_UI),
+        "pt-ui" => Some(PT_UI),
+        "app-specific" | "none" => Some(&[]),
+        _ => None,
+    }
+}
+
+/// Synonym edge to another term
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synonym {
+    pub term: String,
+    /// Score multiplier in `(0, 1]` applied to matches through this edge
+    pub weight: f32,
+}
+
+/// Custom group as accepted by [`SynonymTable::load_json`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GroupSpec {
+    Terms(Vec<String>),
+    Weighted { terms: Vec<String>, weight: f32 },
+}
+
+/// Weighted synonym graph. Groups are symmetric: every term gets an edge to
+/// all other terms of each group it belongs to. Overlapping groups keep the
+/// strongest edge.
+#[derive(Debug, Clone, Default)]
+pub struct SynonymTable {
+    /// Lowercased term -> edges, as added; this is what snapshots store
+    map: FxHashMap<String, Vec<Synonym>>,
+    /// The same graph with terms normalized under `options`, for lookups
+    lookup: FxHashMap<String, Vec<Synonym>>,
+    options: TextOptions,
+}
+
+impl SynonymTable {
+    /// Empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Table preloaded with a built-in profile
+    pub fn with_profile(name: &str) -> Result<Self, String> {
+        let mut table = Self::new();
+        table.add_profile(name)?;
+        Ok(table)
+    }
+
+    /// Merge built-in profiles into the table. Several profiles can be
+    /// combined with `+`, e.g. `"de-en-ui+fr-ui"`.
+    pub fn add_profile(&mut self, name: &str) -> Result<(), String> {
+        let profiles = name
+            .split('+')
+            .map(|n| {
+                let n = n.trim();
+                builtin_profile(n).ok_or_else(|| format!("Unknown synonym profile: {}", n))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for groups in profiles {
+            for (weight, group) in groups {
+                self.add_group(group, *weight);
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge custom groups from JSON. Plain arrays use [`DEFAULT_WEIGHT`]:
+    /// `[["invoice", "bill"], {"terms": ["ok", "submit"], "weight": 0.5}]`
+    pub fn load_json(&mut self, json: &str) -> Result<(), String> {
+        let groups: Vec<GroupSpec> = serde_json::from_str(json)
+            .map_err(|e| format!("Failed to parse synonym groups: {}", e))?;
+
+        // Validate everything before merging so a bad group changes nothing
+        for group in &groups {
+            if let GroupSpec::Weighted { weight, .. } = group {
+                if !(*weight > 0.0 && *weight <= 1.0) {
+                    return Err(format!("Synonym weight must be in (0, 1], got {}", weight));
+                }
+            }
+        }
+        for group in &groups {
+            match group {
+                GroupSpec::Terms(terms) => self.add_group(terms, DEFAULT_WEIGHT),
+                GroupSpec::Weighted { terms, weight } => self.add_group(terms, *weight),
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge one group, extending the synonyms of terms that already exist
+    pub fn add_group<S: AsRef<str>>(&mut self, group: &[S], weight: f32) {
+        let terms: Vec<String> = group
+            .iter()
+            .map(|t| t.as_ref().trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        let keys: Vec<String> = terms.iter().map(|t| normalize(t, &self.options)).collect();
+
+        for term in &terms {
+            for other in &terms {
+                link(&mut self.map, term, other, weight);
+            }
+        }
+        for key in &keys {
+            for other in &keys {
+                link(&mut self.lookup, key, other, weight);
+            }
+        }
+    }
+
+    /// Normalize lookups with `options` ("Löschen" finds the "loeschen"
+    /// synonyms under the default options), re-keying the table if they changed
+    pub fn set_text_options(&mut self, options: TextOptions) {
+        if options == self.options {
+            return;
+        }
+        self.options = options;
+        self.rekey();
+    }
+
+    /// Rebuild the lookup map from the stored terms
+    fn rekey(&mut self) {
+        self.lookup.clear();
+        for (term, others) in &self.map {
+            let key = normalize(term, &self.options);
+            for other in others {
+                link(&mut self.lookup, &key, &normalize(&other.term, &self.options), other.weight);
+            }
+        }
+    }
+
+    /// Synonyms of a term, both in normalized form
+    pub fn get(&self, term: &str) -> Option<&Vec<Synonym>> {
+        self.lookup.get(&normalize(term, &self.options))
+    }
+
+    /// Number of terms with synonyms
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Remove all synonyms
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.lookup.clear();
+    }
+
+    /// Serialize in sorted term order so snapshots are deterministic
+    pub fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        let mut entries: Vec<_> = self.map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        w.put_len(entries.len());
+        for (term, others) in entries {
+            w.put_str(term);
+            w.put_len(others.len());
+            for other in others {
+                w.put_str(&other.term);
+                w.put_f32(other.weight);
+            }
+        }
+    }
+
+    pub fn read_snapshot(r: &mut SnapshotReader) -> Result<Self, String> {
+        let mut map = FxHashMap::default();
+        for _ in 0..r.length()? {
+            let term = r.string()?;
+            let count = r.length()?;
+            let mut others = Vec::with_capacity(count);
+            for _ in 0..count {
+                let other = r.string()?;
+                let weight = r.f32()?;
+                if !(weight > 0.0 && weight <= 1.0) {
+                    return Err(format!("Invalid synonym weight {} for '{}'", weight, term));
+                }
+                others.push(Synonym { term: other, weight });
+            }
+            map.insert(term, others);
+        }
+
+        // Keyed under the default options; the database re-keys it with its own
+        let mut table = SynonymTable { map, ..Self::default() };
+        table.rekey();
+        Ok(table)
+    }
+}
+
+/// Add an edge from `term` to `other`, keeping the stronger weight if it exists
+fn link(map: &mut FxHashMap<String, Vec<Synonym>>, term: &str, other: &str, weight: f32) {
+    if term == other {
+        return;
+    }
+    let entry = map.entry(term.to_string()).or_default();
+    match entry.iter_mut().find(|s| s.term == other) {
+        Some(existing) => existing.weight = existing.weight.max(weight),
+        None => entry.push(Synonym { term: other.to_string(), weight }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has(table: &SynonymTable, term: &str, synonym: &str) -> bool {
+        table.get(term).is_some_and(|syns| syns.iter().any(|s| s.term == synonym))
+    }
+
+    fn weight(table: &SynonymTable, term: &str, synonym: &str) -> f32 {
+        table.get(term).unwrap().iter().find(|s| s.term == synonym).unwrap().weight
+    }
+
+    #[test]
+    fn test_profiles() {
+        let table = SynonymTable::with_profile("de-en-ui").unwrap();
+        assert!(has(&table, "login", "anmelden"));
+        assert_eq!(SynonymTable::with_profile("none").unwrap().len(), 0);
+        assert!(SynonymTable::with_profile("klingon").is_err());
+    }
+
+    #[test]
+    fn test_combined_locale_packs() {
+        let fr = SynonymTable::with_profile("fr-ui").unwrap();
+        assert!(has(&fr, "enregistrer", "save"));
+        assert!(fr.get("speichern").is_none());
+
+        let table = SynonymTable::with_profile("de-en-ui+fr-ui+es-ui").unwrap();
+        for term in ["speichern", "enregistrer", "guardar"] {
+            assert!(has(&table, "save", term), "missing {}", term);
+        }
+        // "Close" words are loose cancel synonyms in every pack, as in de-en
+        let table = SynonymTable::with_profile("de-en-ui+fr-ui+es-ui+it-ui+nl-ui+pt-ui").unwrap();
+        for term in ["schliessen", "fermer", "cerrar", "chiudi", "sluiten", "fechar"] {
+            assert_eq!(weight(&table, "cancel", term), LOOSE, "{}", term);
+        }
+        assert_eq!(weight(&table, "cancel", "annuler"), DEFAULT_WEIGHT);
+
+        // Unknown names anywhere in the list reject the whole profile
+        assert!(SynonymTable::with_profile("fr-ui+xx-ui").is_err());
+    }
+
+    #[test]
+    fn test_load_and_merge_custom_groups() {
+        let mut table = SynonymTable::with_profile("de-en-ui").unwrap();
+        table.load_json(r#"[["Invoice", "bill", "Rechnung"], ["save", "store"]]"#).unwrap();
+
+        let terms: Vec<&str> = table.get("invoice").unwrap().iter().map(|s| s.term.as_str()).collect();
+        assert_eq!(terms, vec!["bill", "rechnung"]);
+        // Merged into the existing group instead of replacing it
+        assert!(has(&table, "save", "speichern"));
+        assert!(has(&table, "save", "store"));
+
+        assert!(table.load_json(r#"{"invoice": "bill"}"#).is_err());
+        table.clear();
+        assert!(table.get("invoice").is_none());
+    }
+
+    #[test]
+    fn test_lookup_uses_text_options() {
+        let mut table = SynonymTable::with_profile("de-en-ui+es-ui").unwrap();
+        assert!(has(&table, "Loeschen", "delete"));
+        assert!(has(&table, "Löschen", "delete"));
+        assert!(has(&table, "delete", "loeschen"));
+        assert!(has(&table, "atras", "back"));
+
+        table.set_text_options(TextOptions { transliterate_german: false, fold_diacritics: false, ..TextOptions::default() });
+        assert!(table.get("loeschen").is_none());
+        assert!(has(&table, "delete", "löschen"));
+        assert!(has(&table, "atrás", "back"));
+    }
+
+    #[test]
+    fn test_weighted_edges() {
+        let mut table = SynonymTable::with_profile("de-en-ui").unwrap();
+        assert_eq!(weight(&table, "submit", "absenden"), DEFAULT_WEIGHT);
+        assert_eq!(weight(&table, "submit", "ok"), 0.6);
+
+        // Overlapping groups keep the strongest edge
+        table.load_json(r#"[{"terms": ["ok", "submit"], "weight": 0.8}, {"terms": ["ok", "submit"], "weight": 0.3}]"#).unwrap();
+        assert_eq!(weight(&table, "ok", "submit"), 0.8);
+
+        assert!(table.load_json(r#"[{"terms": ["a", "b"], "weight": 1.5}]"#).is_err());
+        assert!(!has(&table, "a", "b"));
+    }
+}
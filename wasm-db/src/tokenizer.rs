@@ -1,59 +1,461 @@
 //! Text tokenization for indexing and matching
 
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
+use crate::distance::{levenshtein_bounded, within_typos};
+use crate::phonetic::PhoneticKeys;
 
-/// Tokenize text into lowercase words for indexing
-pub fn tokenize(text: &str) -> Vec<String> {
-    text.unicode_words()
-        .filter(|w| w.len() > 1)
-        .map(|w| w.to_lowercase())
+/// Snowball stemming language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StemLanguage {
+    En,
+    De,
+    Fr,
+    Es,
+    It,
+    Nl,
+    Pt,
+}
+
+impl StemLanguage {
+    /// All languages in declaration order; the position is the compact code
+    pub const ALL: [StemLanguage; 7] = [
+        StemLanguage::En, StemLanguage::De, StemLanguage::Fr, StemLanguage::Es,
+        StemLanguage::It, StemLanguage::Nl, StemLanguage::Pt,
+    ];
+
+    /// Compact numeric code used by binary formats
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Look up a language by its compact code
+    pub fn from_code(code: u8) -> Option<StemLanguage> {
+        Self::ALL.get(code as usize).copied()
+    }
+
+    fn stemmer(self) -> Stemmer {
+        Stemmer::create(match self {
+            StemLanguage::En => Algorithm::English,
+            StemLanguage::De => Algorithm::German,
+            StemLanguage::Fr => Algorithm::French,
+            StemLanguage::Es => Algorithm::Spanish,
+            StemLanguage::It => Algorithm::Italian,
+            StemLanguage::Nl => Algorithm::Dutch,
+            StemLanguage::Pt => Algorithm::Portuguese,
+        })
+    }
+}
+
+/// Case mapping rules, picked from a BCP 47 language tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Locale {
+    /// Unicode default case mapping
+    #[default]
+    Root,
+    /// Turkish and Azerbaijani: "I" lowercases to dotless "ı", "İ" to "i"
+    Turkic,
+}
+
+impl Locale {
+    /// Compact numeric code used by binary formats
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Look up a locale by its compact code
+    pub fn from_code(code: u8) -> Option<Locale> {
+        [Locale::Root, Locale::Turkic].get(code as usize).copied()
+    }
+}
+
+impl From<String> for Locale {
+    fn from(tag: String) -> Self {
+        let language = tag.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+        match language.as_str() {
+            "tr" | "az" => Locale::Turkic,
+            _ => Locale::Root,
+        }
+    }
+}
+
+impl From<Locale> for String {
+    fn from(locale: Locale) -> Self {
+        match locale {
+            Locale::Root => "und",
+            Locale::Turkic => "tr",
+        }.to_string()
+    }
+}
+
+/// Per-database text normalization settings, applied identically when
+/// indexing and when matching queries. NFKC (which also folds full-width
+/// ASCII) always applies, and lowercasing unless a filter asks for
+/// case-sensitive matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TextOptions {
+    /// Spell out German umlauts and ß ("löschen" -> "loeschen")
+    pub transliterate_german: bool,
+    /// Strip remaining diacritics ("café" -> "cafe")
+    pub fold_diacritics: bool,
+    /// Also match words by their stem ("deleting" ~ "delete")
+    pub stemming: Option<StemLanguage>,
+    /// Build a trigram index over context text as well as names
+    pub context_trigrams: bool,
+    /// Locale whose case mapping lowercasing follows
+    pub locale: Locale,
+    /// Keep case when normalizing. Set per filter by queries; indices are
+    /// always built case-insensitively.
+    #[serde(skip)]
+    pub case_sensitive: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions {
+            transliterate_german: true,
+            fold_diacritics: true,
+            stemming: None,
+            context_trigrams: false,
+            locale: Locale::Root,
+            case_sensitive: false,
+        }
+    }
+}
+
+/// Scripts written without spaces between words, segmented into
+/// character bigrams instead of dictionary words
+fn is_unspaced(c: char) -> bool {
+    matches!(c as u32,
+        0x0E00..=0x0EFF       // Thai, Lao
+        | 0x1000..=0x109F     // Myanmar
+        | 0x1780..=0x17FF     // Khmer
+        | 0x3040..=0x30FF     // Hiragana, Katakana
+        | 0x3400..=0x4DBF     // CJK Extension A
+        | 0x4E00..=0x9FFF     // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF     // Hangul syllables
+        | 0xF900..=0xFAFF     // CJK Compatibility Ideographs
+        | 0x20000..=0x2FFFF)  // CJK Extensions B-F
+}
+
+/// Overlapping bigrams of the grapheme clusters in an unspaced run, so Thai
+/// combining vowels and tone marks stay with their consonant; a lone
+/// cluster is kept as is
+fn bigrams(run: &str) -> impl Iterator<Item = String> + '_ {
+    let graphemes: Vec<&str> = run.graphemes(true).collect();
+    let single = (graphemes.len() == 1).then(|| run.to_string());
+    let pairs: Vec<String> = graphemes.windows(2).map(|w| w.concat()).collect();
+    single.into_iter().chain(pairs)
+}
+
+/// Tokenize text into normalized words for indexing. Runs of CJK, Thai and
+/// similar scripts become character bigrams.
+pub fn tokenize(text: &str, opts: &TextOptions) -> Vec<String> {
+    split_words(text, opts, 2)
+}
+
+/// Words of `text` for whole-word matching: [`tokenize`] without dropping
+/// single-character words such as "A" or "7"
+pub fn match_words(text: &str, opts: &TextOptions) -> Vec<String> {
+    split_words(text, opts, 1)
+}
+
+/// Normalized words of at least `min_chars` characters, with unspaced
+/// runs as bigrams
+fn split_words(text: &str, opts: &TextOptions, min_chars: usize) -> Vec<String> {
+    let text = normalize(text, opts);
+    let mut tokens = Vec::new();
+
+    let mut start = 0;
+    let mut unspaced = false;
+    // Flush a run at every script switch and once more at the end
+    let boundaries = text.char_indices()
+        .map(|(i, c)| (i, is_unspaced(c)))
+        .chain(std::iter::once((text.len(), false)));
+    for (i, is_run) in boundaries {
+        if is_run == unspaced && i < text.len() {
+            continue;
+        }
+        let run = &text[start..i];
+        if unspaced {
+            tokens.extend(bigrams(run));
+        } else {
+            tokens.extend(
+                run.unicode_words()
+                    .filter(|w| w.chars().count() >= min_chars)
+                    .map(str::to_string),
+            );
+        }
+        start = i;
+        unspaced = is_run;
+    }
+
+    tokens
+}
+
+/// Split identifiers such as `submitOrderBtn`, `checkout-form__email_input`
+/// or `HTMLDialog` into normalized words
+pub fn split_identifier(text: &str, opts: &TextOptions) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, cur) = (chars[i - 1], chars[i]);
+            // "orderBtn" splits before B; "HTMLDialog" splits before the D
+            let acronym_end = prev.is_uppercase()
+                && cur.is_uppercase()
+                && chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if (prev.is_lowercase() && cur.is_uppercase()) || acronym_end {
+                words.push(chars[start..i].iter().collect::<String>());
+                start = i;
+            }
+        }
+        words.push(chars[start..].iter().collect());
+    }
+
+    words.iter()
+        .map(|w| normalize(w, opts))
+        .filter(|w| w.chars().count() > 1)
         .collect()
 }
 
-/// Normalize text for comparison (lowercase, trim whitespace)
-pub fn normalize(text: &str) -> String {
-    text.trim().to_lowercase()
+/// Stems of the words in `text`; empty when stemming is disabled
+pub fn stems(text: &str, opts: &TextOptions) -> Vec<String> {
+    let Some(lang) = opts.stemming else {
+        return Vec::new();
+    };
+    let stemmer = lang.stemmer();
+    tokenize(text, opts)
+        .iter()
+        .map(|token| stemmer.stem(token).into_owned())
+        .collect()
+}
+
+/// Lowercase a character under the options' locale, or keep it when
+/// matching case-sensitively
+fn lower(c: char, opts: &TextOptions) -> impl Iterator<Item = char> {
+    let mapped = match (opts.locale, c) {
+        _ if opts.case_sensitive => Some(c),
+        (Locale::Turkic, 'I') => Some('ı'),
+        (Locale::Turkic, 'İ') => Some('i'),
+        _ => None,
+    };
+    let default = if mapped.is_none() { Some(c.to_lowercase()) } else { None };
+    mapped.into_iter().chain(default.into_iter().flatten())
+}
+
+/// Lowercase text as [`normalize`] does, without the other normalization steps
+pub fn lowercase(text: &str, opts: &TextOptions) -> String {
+    text.chars().flat_map(|c| lower(c, opts)).collect()
 }
 
-/// Calculate Levenshtein distance between two strings
-pub fn levenshtein(a: &str, b: &str) -> usize {
-    let a_chars: Vec<char> = a.chars().collect();
-    let b_chars: Vec<char> = b.chars().collect();
-    let a_len = a_chars.len();
-    let b_len = b_chars.len();
+/// Normalize text for comparison: NFKC, lowercase (locale-aware, skipped
+/// when case-sensitive), trim, then the optional German transliteration and
+/// diacritic folding
+pub fn normalize(text: &str, opts: &TextOptions) -> String {
+    let text = text.trim();
+    if text.is_ascii() && !(opts.locale == Locale::Turkic && text.contains('I')) {
+        return if opts.case_sensitive { text.to_string() } else { text.to_ascii_lowercase() };
+    }
+
+    let mut out = String::with_capacity(text.len());
+    for c in text.nfkc().flat_map(|c| lower(c, opts)) {
+        match c {
+            'ä' if opts.transliterate_german => out.push_str("ae"),
+            'ö' if opts.transliterate_german => out.push_str("oe"),
+            'ü' if opts.transliterate_german => out.push_str("ue"),
+            'ß' if opts.transliterate_german => out.push_str("ss"),
+            // Only reachable when case is kept
+            'Ä' if opts.transliterate_german => out.push_str("Ae"),
+            'Ö' if opts.transliterate_german => out.push_str("Oe"),
+            'Ü' if opts.transliterate_german => out.push_str("Ue"),
+            _ => out.push(c),
+        }
+    }
 
-    if a_len == 0 {
-        return b_len;
+    if opts.fold_diacritics {
+        // Only Latin-style accents; kana voicing marks and Thai vowels stay
+        out = out.nfd().filter(|c| !('\u{300}'..='\u{36f}').contains(c)).nfc().collect();
     }
-    if b_len == 0 {
-        return a_len;
+    // NFKC can expand to leading/trailing spaces (e.g. U+3000)
+    let trimmed = out.trim();
+    if trimmed.len() != out.len() {
+        out = trimmed.to_string();
     }
+    out
+}
+
+/// Normalized characters of `text`, each with the UTF-16 range of the
+/// original character it came from. Characters are normalized one at a
+/// time, so whitespace is kept and the result lines up with [`normalize`]
+/// up to trimming.
+fn normalize_with_offsets(text: &str, opts: &TextOptions) -> Vec<(char, (usize, usize))> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut pos = 0;
+    for c in text.chars() {
+        let span = (pos, pos + c.len_utf16());
+        pos = span.1;
+        let piece: String = if c.is_whitespace() {
+            std::iter::once(c).nfkc().collect()
+        } else {
+            normalize(c.encode_utf8(&mut [0; 4]), opts)
+        };
+        out.extend(piece.chars().map(|n| (n, span)));
+    }
+    out
+}
 
-    let mut matrix = vec![vec![0usize; b_len + 1]; a_len + 1];
+/// First occurrence of `needle` in `haystack` at or after `from`, as a char range
+fn find_chars(haystack: &[char], needle: &[char], from: usize) -> Option<(usize, usize)> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    (from..=haystack.len() - needle.len())
+        .find(|&i| haystack[i..i + needle.len()] == *needle)
+        .map(|i| (i, i + needle.len()))
+}
 
-    for i in 0..=a_len {
-        matrix[i][0] = i;
+/// Runs of letters and digits, as char ranges
+fn word_ranges(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in chars.iter().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, chars.len()));
     }
-    for j in 0..=b_len {
-        matrix[0][j] = j;
+    words
+}
+
+/// Align each pattern word with the text: a substring hit, or else a text
+/// word within typos, with the same stem or sounding alike
+fn align_words(text: &[char], pattern: &[char], opts: &TextOptions) -> Vec<(usize, usize)> {
+    let text_words = word_ranges(text);
+    let word = |range: (usize, usize), chars: &[char]| chars[range.0..range.1].iter().collect::<String>();
+
+    word_ranges(pattern).into_iter()
+        .filter_map(|range| {
+            let p = &pattern[range.0..range.1];
+            if let Some(hit) = find_chars(text, p, 0) {
+                return Some(hit);
+            }
+            let p = word(range, pattern);
+            let p_stems = stems(&p, opts);
+            let p_sound = PhoneticKeys::of(&p, opts);
+            text_words.iter().copied().find(|&w| {
+                let t = word(w, text);
+                within_typos(&p, &t, max_typos(p.chars().count()))
+                    || (!p_stems.is_empty() && p_stems == stems(&t, opts))
+                    || p_sound.matches(&PhoneticKeys::of(&t, opts))
+            })
+        })
+        .collect()
+}
+
+/// UTF-16 ranges of `text` that `pattern` matched under `match_type`, merged
+/// and in order. Substring-style matches map back exactly; fuzzy, stem and
+/// phonetic matches highlight the aligned words.
+pub fn highlight(text: &str, pattern: &str, match_type: &str, opts: &TextOptions) -> Vec<(usize, usize)> {
+    let normalized = normalize_with_offsets(text, opts);
+    let chars: Vec<char> = normalized.iter().map(|&(c, _)| c).collect();
+    let pat: Vec<char> = normalize(pattern, opts).chars().collect();
+    if pat.is_empty() {
+        return Vec::new();
     }
 
-    for i in 1..=a_len {
-        for j in 1..=b_len {
-            let cost = if a_chars[i - 1] == b_chars[j - 1] { 0 } else { 1 };
-            matrix[i][j] = (matrix[i - 1][j] + 1)
-                .min(matrix[i][j - 1] + 1)
-                .min(matrix[i - 1][j - 1] + cost);
+    let mut ranges: Vec<(usize, usize)> = match match_type {
+        "ends_with" => (0..=chars.len().saturating_sub(pat.len()))
+            .rev()
+            .find(|&i| chars[i..].starts_with(&pat))
+            .map(|i| (i, i + pat.len()))
+            .into_iter()
+            .collect(),
+        "word" => {
+            let boundary = |i: usize| chars.get(i).is_none_or(|c| !c.is_alphanumeric());
+            let mut from = 0;
+            let mut hit = None;
+            while let Some((s, e)) = find_chars(&chars, &pat, from) {
+                if (s == 0 || boundary(s - 1)) && boundary(e) {
+                    hit = Some((s, e));
+                    break;
+                }
+                from = s + 1;
+            }
+            hit.into_iter().collect()
+        }
+        "glob" => {
+            // Literal pieces between wildcards, in order
+            let mut from = 0;
+            pat.split(|&c| c == '*' || c == '?')
+                .filter(|piece| !piece.is_empty())
+                .map_while(|piece| {
+                    let hit = find_chars(&chars, piece, from)?;
+                    from = hit.1;
+                    Some(hit)
+                })
+                .collect()
+        }
+        "exact" | "starts_with" | "regex" => find_chars(&chars, &pat, 0).into_iter().collect(),
+        _ => match find_chars(&chars, &pat, 0) {
+            Some(hit) => vec![hit],
+            None => {
+                let aligned = align_words(&chars, &pat, opts);
+                // Phonetic and LSH matches can be phrase-level ("lock out" ~
+                // "Logout"); unless every pattern word aligns, take the whole text
+                let partial = aligned.len() < word_ranges(&pat).len();
+                if matches!(match_type, "phonetic" | "lsh") && partial {
+                    let first = chars.iter().position(|c| !c.is_whitespace());
+                    let last = chars.iter().rposition(|c| !c.is_whitespace());
+                    first.zip(last).map(|(s, e)| (s, e + 1)).into_iter().collect()
+                } else {
+                    aligned
+                }
+            }
+        },
+    };
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (s, e) in ranges {
+        let span = (normalized[s].1.0, normalized[e - 1].1.1);
+        match merged.last_mut() {
+            Some(last) if span.0 <= last.1 => last.1 = last.1.max(span.1),
+            _ => merged.push(span),
         }
     }
+    merged
+}
+
+/// Credit for a query word that only matches a target word up to a typo
+const TYPO_CREDIT: f64 = 0.85;
 
-    matrix[a_len][b_len]
+/// Typos tolerated in a word of `len` characters
+pub fn max_typos(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
 }
 
 /// Calculate fuzzy match score (0.0 - 1.0)
-pub fn fuzzy_score(query: &str, target: &str) -> f64 {
-    let q = normalize(query);
-    let t = normalize(target);
+pub fn fuzzy_score(query: &str, target: &str, opts: &TextOptions) -> f64 {
+    let q = normalize(query, opts);
+    let t = normalize(target, opts);
 
     // Exact match
     if t == q {
@@ -66,69 +468,123 @@ pub fn fuzzy_score(query: &str, target: &str) -> f64 {
     }
 
     // Token overlap
-    let q_tokens = tokenize(&q);
-    let t_tokens = tokenize(&t);
+    let q_tokens = tokenize(&q, opts);
+    let t_tokens = tokenize(&t, opts);
     
     if q_tokens.is_empty() {
         return 0.0;
     }
 
-    let overlap = q_tokens.iter()
-        .filter(|qt| t_tokens.iter().any(|tt| tt.contains(qt.as_str()) || qt.contains(tt.as_str())))
-        .count();
-    let token_score = overlap as f64 / q_tokens.len() as f64;
+    // Whole-word containment counts fully, a near miss ("lgoin") partially
+    let credit: f64 = q_tokens.iter()
+        .map(|qt| {
+            if t_tokens.iter().any(|tt| tt.contains(qt.as_str()) || qt.contains(tt.as_str())) {
+                1.0
+            } else {
+                let typos = max_typos(qt.chars().count());
+                if typos > 0 && t_tokens.iter().any(|tt| within_typos(qt, tt, typos)) {
+                    TYPO_CREDIT
+                } else {
+                    0.0
+                }
+            }
+        })
+        .sum();
+    let token_score = credit / q_tokens.len() as f64;
 
-    // Levenshtein-based similarity (on truncated target)
-    let t_truncated: String = t.chars().take(q.len() + 10).collect();
-    let distance = levenshtein(&q, &t_truncated);
-    let max_len = q.len().max(t_truncated.len());
-    let lev_score = if max_len > 0 {
-        1.0 - (distance as f64 / max_len as f64)
-    } else {
-        0.0
+    // Levenshtein-based similarity (on truncated target), in characters.
+    // Only distances that could beat the token score are computed in full.
+    let q_len = q.chars().count();
+    let t_truncated: String = t.chars().take(q_len + 10).collect();
+    let max_len = q_len.max(t_truncated.chars().count());
+    let useful = (max_len as f64 * (1.0 - token_score * 1.4)).ceil().max(0.0) as usize;
+    let lev_score = match levenshtein_bounded(&q, &t_truncated, useful) {
+        Some(distance) if max_len > 0 => 1.0 - (distance as f64 / max_len as f64),
+        _ => 0.0,
     };
 
     (token_score * 0.7).max(lev_score * 0.5)
 }
 
 /// Check if text matches pattern using specified match type
-pub fn match_text(text: &str, patterns: &[String], match_type: &str) -> bool {
-    let text_lower = normalize(text);
+pub fn match_text(text: &str, patterns: &[String], match_type: &str, opts: &TextOptions) -> bool {
+    let text_lower = normalize(text, opts);
     
-    for pattern in patterns {
-        let pattern_lower = normalize(pattern);
-        
-        match match_type {
-            "exact" => {
-                if text_lower == pattern_lower {
-                    return true;
-                }
-            }
-            "contains" => {
-                if text_lower.contains(&pattern_lower) {
-                    return true;
-                }
-            }
-            "fuzzy" => {
-                if fuzzy_score(&pattern_lower, &text_lower) > 0.5 {
-                    return true;
-                }
-            }
-            "regex" => {
-                // Fallback to contains for regex (full regex would add dependencies)
-                if text_lower.contains(&pattern_lower) {
-                    return true;
-                }
-            }
-            _ => {
-                if text_lower.contains(&pattern_lower) {
-                    return true;
-                }
-            }
+    patterns.iter().any(|pattern| is_match(&text_lower, &normalize(pattern, opts), match_type, opts))
+}
+
+/// Match one normalized pattern against normalized text
+fn is_match(text_lower: &str, pattern_lower: &str, match_type: &str, opts: &TextOptions) -> bool {
+    match match_type {
+        "exact" => text_lower == pattern_lower,
+        "contains" => text_lower.contains(pattern_lower),
+        // LSH needs a signature index; matched directly it behaves as fuzzy
+        "fuzzy" | "lsh" => fuzzy_score(pattern_lower, text_lower, opts) > 0.5,
+        "phonetic" => PhoneticKeys::of(pattern_lower, opts).matches(&PhoneticKeys::of(text_lower, opts)),
+        "starts_with" => text_lower.starts_with(pattern_lower),
+        "ends_with" => text_lower.ends_with(pattern_lower),
+        "word" => word_match(pattern_lower, text_lower, opts),
+        "glob" => glob_match(pattern_lower, text_lower),
+        // Fallback to contains for regex (full regex would add dependencies)
+        _ => text_lower.contains(pattern_lower),
+    }
+}
+
+/// Whether the pattern's words appear as consecutive whole words of the text
+fn word_match(pattern: &str, text: &str, opts: &TextOptions) -> bool {
+    let words = match_words(pattern, opts);
+    if words.is_empty() {
+        return false;
+    }
+    match_words(text, opts).windows(words.len()).any(|window| window == words.as_slice())
+}
+
+/// Glob match over the whole text: `*` is any run of characters, `?` one character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Last `*` seen and the text position it currently absorbs up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        // A `*` is a wildcard even where the text has a literal `*`
+        if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            // Let the star absorb one more character and retry
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
         }
     }
-    
-    false
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Score of `pattern` against `text` for a match type. Prefix, suffix, word
+/// and glob matches score by how much of the text the pattern covers; other
+/// types use [`fuzzy_score`].
+pub fn match_score(pattern: &str, text: &str, match_type: &str, opts: &TextOptions) -> f64 {
+    if !matches!(match_type, "starts_with" | "ends_with" | "word" | "glob") {
+        return fuzzy_score(pattern, text, opts);
+    }
+
+    let p = normalize(pattern, opts);
+    let t = normalize(text, opts);
+    if !is_match(&t, &p, match_type, opts) {
+        return 0.0;
+    }
+    if t == p {
+        return 1.0;
+    }
+    let literal = p.chars().filter(|c| !matches!(c, '*' | '?')).count();
+    0.8 + 0.2 * literal as f64 / t.chars().count().max(1) as f64
 }
 
 #[cfg(test)]
@@ -137,7 +593,7 @@ mod tests {
 
     #[test]
     fn test_tokenize() {
-        let tokens = tokenize("Hello World! This is a test.");
+        let tokens = tokenize("Hello World! This is a test.", &TextOptions::default());
         assert!(tokens.contains(&"hello".to_string()));
         assert!(tokens.contains(&"world".to_string()));
         assert!(tokens.contains(&"test".to_string()));
@@ -146,9 +602,123 @@ mod tests {
 
     #[test]
     fn test_fuzzy_score() {
-        assert_eq!(fuzzy_score("login", "login"), 1.0);
-        assert!(fuzzy_score("login", "Login Button") > 0.8);
+        let opts = TextOptions::default();
+        assert_eq!(fuzzy_score("login", "login", &opts), 1.0);
+        assert!(fuzzy_score("login", "Login Button", &opts) > 0.8);
         // Typo matching - Levenshtein distance of 1 on 4-char query
-        assert!(fuzzy_score("logn", "login") > 0.3);
+        assert!(fuzzy_score("logn", "login", &opts) > 0.3);
+        // A transposition is a single typo
+        assert!(match_text("Login", &["lgoin".to_string()], "fuzzy", &opts));
+        assert!(!match_text("Logout", &["lgoin".to_string()], "fuzzy", &opts));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let opts = TextOptions::default();
+        assert_eq!(normalize("Löschen", &opts), normalize("Loeschen", &opts));
+        assert_eq!(normalize("Schließen", &opts), "schliessen");
+        // Composed and decomposed é, both folded
+        assert_eq!(normalize("Caf\u{e9}", &opts), "cafe");
+        assert_eq!(normalize("Cafe\u{301}", &opts), "cafe");
+        assert_eq!(normalize("ＳＡＶＥ\u{3000}", &opts), "save");
+
+        let plain = TextOptions { transliterate_german: false, fold_diacritics: false, ..TextOptions::default() };
+        assert_eq!(normalize("Cafe\u{301}", &plain), "caf\u{e9}");
+        assert_eq!(normalize("Löschen", &plain), "löschen");
+        assert!(match_text("Loeschen", &["LÖSCHEN".to_string()], "exact", &opts));
+    }
+
+    #[test]
+    fn test_locale_and_case() {
+        let turkish = TextOptions { locale: Locale::from("tr-TR".to_string()), ..TextOptions::default() };
+        assert_eq!(normalize("KAPI", &turkish), "kapı");
+        assert_eq!(normalize("DİKKAT", &turkish), "dikkat");
+        assert_eq!(normalize("KAPI", &TextOptions::default()), "kapi");
+        assert!(match_text("Kapı", &["KAPI".to_string()], "exact", &turkish));
+
+        let cased = TextOptions { case_sensitive: true, ..TextOptions::default() };
+        assert_eq!(normalize(" Größe ", &cased), "Groesse");
+        assert!(match_text("User ID", &["ID".to_string()], "word", &cased));
+        assert!(!match_text("Valid", &["ID".to_string()], "contains", &cased));
+        assert!(!match_text("Ship to us", &["US".to_string()], "word", &cased));
+    }
+
+    #[test]
+    fn test_highlight_utf16_ranges() {
+        let opts = TextOptions::default();
+        assert_eq!(highlight("Löschen", "loeschen", "contains", &opts), vec![(0, 7)]);
+        assert_eq!(highlight("😀 Save changes", "save", "contains", &opts), vec![(3, 7)]);
+        assert_eq!(highlight("Unsaved, Save", "save", "word", &opts), vec![(9, 13)]);
+        assert_eq!(highlight("Autosave", "save", "ends_with", &opts), vec![(4, 8)]);
+        assert_eq!(highlight("Save draft now", "save*now", "glob", &opts), vec![(0, 4), (11, 14)]);
+
+        // Fuzzy and phonetic matches highlight the aligned words
+        assert_eq!(highlight("Please lgoin now", "login", "fuzzy", &opts), vec![(7, 12)]);
+        assert_eq!(highlight("Save all changes", "save changs", "fuzzy", &opts), vec![(0, 4), (9, 16)]);
+        assert_eq!(highlight("Logout", "lock out", "phonetic", &opts), vec![(0, 6)]);
+    }
+
+    #[test]
+    fn test_unspaced_scripts() {
+        let opts = TextOptions::default();
+        assert_eq!(tokenize("保存する", &opts), vec!["保存", "存す", "する"]);
+        assert_eq!(tokenize("Login ログイン 円", &opts), vec!["login", "ログ", "グイ", "イン", "円"]);
+        assert_eq!(tokenize("ปุ่ม OK", &opts), vec!["ปุ่ม", "ok"]);
+        assert_eq!(tokenize("บันทึก", &opts), vec!["บัน", "นทึ", "ทึก"]);
+
+        assert!(match_text("設定を保存する", &["設定 保存".to_string()], "fuzzy", &opts));
+        assert!(!match_text("設定を保存する", &["ヘルプ".to_string()], "fuzzy", &opts));
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        let opts = TextOptions::default();
+        assert_eq!(split_identifier("submitOrderBtn", &opts), vec!["submit", "order", "btn"]);
+        assert_eq!(split_identifier("checkout-form__email_input", &opts), vec!["checkout", "form", "email", "input"]);
+        assert_eq!(split_identifier("HTMLDialog--open", &opts), vec!["html", "dialog", "open"]);
+        assert_eq!(split_identifier("Close menu", &opts), vec!["close", "menu"]);
+
+        // Single characters are dropped however many bytes they take
+        let plain = TextOptions { fold_diacritics: false, ..TextOptions::default() };
+        assert_eq!(split_identifier("item-é", &plain), vec!["item"]);
+    }
+
+    #[test]
+    fn test_anchored_and_glob_matches() {
+        let opts = TextOptions::default();
+        let m = |text: &str, pattern: &str, match_type: &str| match_text(text, &[pattern.to_string()], match_type, &opts);
+
+        assert!(m("Save changes", "save", "starts_with"));
+        assert!(!m("Autosave", "save", "starts_with"));
+        assert!(m("Autosave", "save", "ends_with"));
+        assert!(m("Save changes now", "save changes", "word"));
+        assert!(!m("Unsaved changes", "save", "word"));
+        // Single-character words count too
+        assert!(m("Plan A", "plan a", "word"));
+        assert!(!m("Plan B", "plan a", "word"));
+        assert!(m("Item 7", "7", "word"));
+        assert!(!m("Item 17", "7", "word"));
+        assert!(m("Save draft", "Save*", "glob"));
+        assert!(m("Item 7", "Item ?", "glob"));
+        assert!(!m("Item 12", "Item ?", "glob"));
+        assert!(m("Delete item 3", "*item*", "glob"));
+        assert!(!m("Save draft", "*draf", "glob"));
+        // A literal `*` in the text doesn't turn the pattern's `*` literal
+        assert!(m("* Required fields", "*required*", "glob"));
+        assert!(m("* Required fields", "*", "glob"));
+
+        assert_eq!(match_score("save", "Save", "starts_with", &opts), 1.0);
+        assert!(match_score("save", "Save", "word", &opts) > match_score("save", "Save all changes", "word", &opts));
+        assert_eq!(match_score("save", "Unsaved", "word", &opts), 0.0);
+    }
+
+    #[test]
+    fn test_stems() {
+        let en = TextOptions { stemming: Some(StemLanguage::En), ..TextOptions::default() };
+        assert_eq!(stems("Deleting items", &en), vec!["delet", "item"]);
+        assert!(stems("Deleting items", &TextOptions::default()).is_empty());
+
+        let de = TextOptions { stemming: Some(StemLanguage::De), ..TextOptions::default() };
+        assert_eq!(stems("Einstellung", &de), stems("Einstellungen", &de));
     }
 }
@@ -0,0 +1,113 @@
+//! Character trigram index for shortlisting substring and fuzzy matches
+
+use rustc_hash::FxHashMap;
+use crate::bitset::Bitset;
+use crate::tokenizer::{normalize, TextOptions};
+
+/// Pack three characters into one key (21 bits each)
+fn key(a: char, b: char, c: char) -> u64 {
+    ((a as u64) << 42) | ((b as u64) << 21) | c as u64
+}
+
+/// Trigrams of normalized text, optionally padded with a space on each side
+/// so short words and word edges get trigrams too
+pub fn trigrams(text: &str, padded: bool) -> Vec<u64> {
+    let mut chars: Vec<char> = Vec::with_capacity(text.len() + 2);
+    if padded {
+        chars.push(' ');
+    }
+    chars.extend(text.chars());
+    if padded {
+        chars.push(' ');
+    }
+
+    let mut keys: Vec<u64> = chars.windows(3).map(|w| key(w[0], w[1], w[2])).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
+
+/// Trigram -> record indices over one text column
+#[derive(Debug, Clone, Default)]
+pub struct TrigramIndex {
+    postings: FxHashMap<u64, Vec<usize>>,
+    len: usize,
+}
+
+impl TrigramIndex {
+    /// Index the padded trigrams of each text; `texts` yields one entry per record
+    pub fn build<'t>(texts: impl Iterator<Item = &'t str>, opts: &TextOptions) -> Self {
+        let mut postings: FxHashMap<u64, Vec<usize>> = FxHashMap::default();
+        let mut len = 0;
+        for (idx, text) in texts.enumerate() {
+            for trigram in trigrams(&normalize(text, opts), true) {
+                postings.entry(trigram).or_default().push(idx);
+            }
+            len = idx + 1;
+        }
+        TrigramIndex { postings, len }
+    }
+
+    /// Records that may match one of `patterns`, or `None` when the index
+    /// can't narrow the search (a pattern too short to have trigrams).
+    ///
+    /// With `require_all` a record must contain every trigram of a pattern,
+    /// which is exact for substring matches. Otherwise one shared padded
+    /// trigram is enough, which suits fuzzy matching; callers verify every
+    /// shortlisted record.
+    pub fn shortlist<'p>(
+        &self,
+        patterns: impl Iterator<Item = &'p str>,
+        require_all: bool,
+        opts: &TextOptions,
+    ) -> Option<Bitset> {
+        let mut result = Bitset::empty(self.len);
+
+        for pattern in patterns {
+            let keys = trigrams(&normalize(pattern, opts), !require_all);
+            if keys.is_empty() {
+                return None;
+            }
+
+            let mut matching = if require_all { Bitset::full(self.len) } else { Bitset::empty(self.len) };
+            for trigram in keys {
+                let mut posting = Bitset::empty(self.len);
+                for &idx in self.postings.get(&trigram).map_or(&[][..], Vec::as_slice) {
+                    posting.insert(idx);
+                }
+                if require_all {
+                    matching.intersect_with(&posting);
+                } else {
+                    matching.union_with(&posting);
+                }
+            }
+            result.union_with(&matching);
+        }
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortlist() {
+        let opts = TextOptions::default();
+        let names = ["Save changes", "Cancel", "Autosave", "Ok"];
+        let index = TrigramIndex::build(names.iter().copied(), &opts);
+
+        let contains = index.shortlist(["save"].into_iter(), true, &opts).unwrap();
+        assert_eq!(contains.iter().collect::<Vec<_>>(), vec![0, 2]);
+
+        // A typo still shares trigrams with the intended word
+        let fuzzy = index.shortlist(["cancle"].into_iter(), false, &opts).unwrap();
+        assert!(fuzzy.contains(1));
+        assert!(!fuzzy.contains(0));
+
+        // Too short to narrow down a substring search
+        assert!(index.shortlist(["ok"].into_iter(), true, &opts).is_none());
+        assert!(index.shortlist(["ok"].into_iter(), false, &opts).unwrap().contains(3));
+    }
+}
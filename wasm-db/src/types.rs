@@ -49,6 +49,36 @@ pub enum ElementRole {
     Generic,
 }
 
+impl ElementRole {
+    /// All roles in declaration order; the position is the role's compact code
+    pub const ALL: [ElementRole; 41] = [
+        ElementRole::Button, ElementRole::Link, ElementRole::Textbox,
+        ElementRole::Checkbox, ElementRole::Radio, ElementRole::Combobox,
+        ElementRole::Listbox, ElementRole::Option, ElementRole::Menu,
+        ElementRole::Menuitem, ElementRole::Tab, ElementRole::Tabpanel,
+        ElementRole::Dialog, ElementRole::Alertdialog, ElementRole::Switch,
+        ElementRole::Slider, ElementRole::Spinbutton, ElementRole::Searchbox,
+        ElementRole::Heading, ElementRole::Image, ElementRole::Navigation,
+        ElementRole::Main, ElementRole::Region, ElementRole::Form,
+        ElementRole::Grid, ElementRole::Gridcell, ElementRole::Row,
+        ElementRole::Rowgroup, ElementRole::Cell, ElementRole::Columnheader,
+        ElementRole::Rowheader, ElementRole::Tree, ElementRole::Treeitem,
+        ElementRole::Tooltip, ElementRole::Status, ElementRole::Alert,
+        ElementRole::Progressbar, ElementRole::Separator, ElementRole::Group,
+        ElementRole::Article, ElementRole::Generic,
+    ];
+
+    /// Compact numeric code used by binary formats
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Look up a role by its compact code
+    pub fn from_code(code: u8) -> Option<ElementRole> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
 /// State flags as bitfield constants
 pub mod state_flags {
     pub const VISIBLE: u32 = 1 << 0;
@@ -64,10 +94,13 @@ pub mod state_flags {
     pub const BUSY: u32 = 1 << 10;
     pub const HIDDEN: u32 = 1 << 11;
     pub const DISABLED: u32 = 1 << 12;
+
+    /// Number of defined flags (bits `0..COUNT`)
+    pub const COUNT: usize = 13;
 }
 
 /// Bounding rectangle
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -95,12 +128,40 @@ pub struct NodeRecord {
 
 /// Match type for text filtering
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub enum MatchType {
     Exact,
     Contains,
     Fuzzy,
     Regex,
+    /// Approximate near-duplicate names via MinHash/LSH
+    Lsh,
+    /// Sound-alike words via Double Metaphone and Kölner Phonetik codes
+    Phonetic,
+    StartsWith,
+    EndsWith,
+    /// Whole words only, so "Save" doesn't match "Unsaved"
+    Word,
+    /// Wildcards over the whole text: `*` any run, `?` one character
+    Glob,
+}
+
+impl MatchType {
+    /// Lowercase name, as used in queries and explain output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchType::Exact => "exact",
+            MatchType::Contains => "contains",
+            MatchType::Fuzzy => "fuzzy",
+            MatchType::Regex => "regex",
+            MatchType::Lsh => "lsh",
+            MatchType::Phonetic => "phonetic",
+            MatchType::StartsWith => "starts_with",
+            MatchType::EndsWith => "ends_with",
+            MatchType::Word => "word",
+            MatchType::Glob => "glob",
+        }
+    }
 }
 
 /// Query filter clauses
@@ -144,6 +205,9 @@ pub struct TextFilter {
     #[serde(rename = "match")]
     pub match_type: MatchType,
     pub value: String,
+    /// Match case exactly ("ID" but not "id")
+    #[serde(default)]
+    pub case_sensitive: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -152,6 +216,9 @@ pub struct AttrFilter {
     pub value: String,
     #[serde(rename = "match", default)]
     pub match_type: Option<MatchType>,
+    /// Match case exactly
+    #[serde(default)]
+    pub case_sensitive: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -189,6 +256,33 @@ pub struct QueryAST {
     pub limit: Option<usize>,
     #[serde(default)]
     pub offset: Option<usize>,
+    /// Retry a query without matches using the spelling suggestions
+    #[serde(default)]
+    pub autocorrect: bool,
+    /// Weaken a query without matches until something matches
+    #[serde(default)]
+    pub relax: bool,
+    /// Score gap under which leading matches are reported as ambiguous
+    #[serde(default)]
+    pub ambiguity_gap: Option<f64>,
+    /// Drop matches whose reported score (see `score_mode`) is below this
+    #[serde(default)]
+    pub min_score: Option<f64>,
+    #[serde(default)]
+    pub score_mode: ScoreMode,
+}
+
+/// How match scores are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreMode {
+    /// Absolute scores capped at 1.0
+    #[default]
+    Clamped,
+    /// Absolute scores without the cap, so strong matches don't tie
+    Raw,
+    /// Softmax over all matching candidates: relative confidences summing to 1
+    Softmax,
 }
 
 /// Actionability flags for an element
@@ -227,6 +321,54 @@ pub struct MatchResult {
     pub context: Vec<String>,
     pub actionability: Actionability,
     pub rect: Rect,
+    /// Text ranges the name, context and attr filters matched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<Highlight>,
+}
+
+/// Part of a record's text that a filter matched. Offsets are UTF-16 code
+/// units into the original text, as JS string indices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highlight {
+    /// "name", "context" or "attr"
+    pub field: String,
+    /// Context entry the range falls in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Attribute whose value the range falls in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attr: Option<String>,
+    pub start: usize,
+    pub end: usize,
+    /// Query text, or the synonym it expanded to, that matched
+    pub term: String,
+}
+
+/// Indexed word and the number of records containing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TermFrequency {
+    pub term: String,
+    pub frequency: usize,
+}
+
+/// Synonym that matched at least one candidate of a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SynonymUse {
+    /// Term from the query
+    pub term: String,
+    /// Synonym the term was expanded to
+    pub synonym: String,
+    /// Edge weight applied to the match score
+    pub weight: f64,
+}
+
+/// Page words close to a query word that matches nothing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellingSuggestion {
+    /// Normalized word from the query
+    pub token: String,
+    /// Nearest page words, closest and most frequent first
+    pub suggestions: Vec<String>,
 }
 
 /// Query execution explanation
@@ -236,6 +378,17 @@ pub struct QueryExplain {
     pub candidates_considered: usize,
     pub filters_applied: Vec<String>,
     pub execution_time_ms: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub synonyms_used: Vec<SynonymUse>,
+    /// "Did you mean" words for the unknown name words of a query without matches
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spelling_suggestions: Vec<SpellingSuggestion>,
+    /// Whether the matches come from retrying with the suggested words
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub autocorrected: bool,
+    /// Relaxations applied, in order, to a query that matched nothing as given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relaxations: Vec<String>,
 }
 
 /// Full query result
@@ -243,5 +396,36 @@ pub struct QueryExplain {
 pub struct QueryResult {
     pub matches: Vec<MatchResult>,
     pub total: usize,
+    /// Present when the leading matches score too close to tell apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ambiguity: Option<Ambiguity>,
     pub explain: QueryExplain,
 }
+
+/// Leading matches whose scores are too close to tell apart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ambiguity {
+    /// Score difference between the best and the last near-tied match
+    pub score_gap: f64,
+    pub candidates: Vec<Disambiguation>,
+}
+
+/// What sets one of several near-tied matches apart from the others
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disambiguation {
+    pub id: u32,
+    /// Context strings no other near-tied match has
+    pub context: Vec<String>,
+    /// Name of the closest heading above the element
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    /// 1-based position among the near-tied matches, top to bottom then left to right
+    pub position: usize,
+    pub rect: Rect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub testid: Option<String>,
+    /// Clauses that, added to the query, keep this match and drop the other near-tied ones
+    pub suggested_where: Vec<WhereClause>,
+}